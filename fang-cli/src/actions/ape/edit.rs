@@ -0,0 +1,65 @@
+use clap::Parser;
+use fang::{
+    ape::{patch::ApePatch, Ape},
+    mst::patch::MstPatcher,
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::Cursor,
+};
+
+use super::ApeSource;
+
+#[derive(Parser, Debug)]
+pub struct EditOpts {
+    /// Path to Ape, or archive.mst:model.ape to edit it inside an archive
+    #[clap(short = 'i', long)]
+    input_path: String,
+    /// Path to an MST to edit the Ape in, input path is then the entry name
+    #[clap(short = 'a', long)]
+    archive: Option<String>,
+    /// Path to a JSON file with the changes to make
+    #[clap(short = 'p', long)]
+    patch_path: String,
+    /// Path to write the edited Ape to, can be the same as the input. Apes from an archive are written
+    /// back into the archive if not given
+    #[clap(short = 'o', long)]
+    output_path: Option<String>,
+}
+
+pub fn edit_ape(opts: EditOpts) -> anyhow::Result<()> {
+    let source = ApeSource::new(&opts.input_path, opts.archive.as_deref());
+    let patch: ApePatch = serde_json::from_reader(File::open(&opts.patch_path)?)?;
+
+    let (data, archive_is_little) = source.read_data()?;
+    let mut data = Cursor::new(data);
+    let is_little = match archive_is_little {
+        Some(is_little) => is_little,
+        None => Ape::detect_little_endian(&mut data)?,
+    };
+    let mut ape = Ape::read_endian(&mut data, is_little)?;
+
+    patch.apply(&mut ape)?;
+    ape.write_in_place(&mut data, is_little)?;
+
+    if let Some(output_path) = &opts.output_path {
+        std::fs::write(output_path, data.into_inner())?;
+    } else if let ApeSource::Archive {
+        archive_path,
+        entry,
+    } = &source
+    {
+        // The edited ape is the same size, so it's written over the old one unless its data is shared
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(archive_path)?;
+        let mut patcher = MstPatcher::new(file)?;
+        patcher.replace_entry(entry, data.get_ref(), None)?;
+        patcher.finish()?;
+    } else {
+        anyhow::bail!("an output path is required when editing an Ape file");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use clap::Parser;
+use fang::ape::gltf::GltfDocument;
+use std::{fs::File, io::BufWriter, str::FromStr};
+
+use super::ApeSource;
+
+#[derive(Parser, Debug)]
+pub struct ExportOpts {
+    /// Path to Ape, or archive.mst:model.ape to read it from an archive
+    #[clap(short = 'i', long)]
+    input_path: String,
+    /// Path to an MST to read the Ape from, input path is then the entry name
+    #[clap(short = 'a', long)]
+    archive: Option<String>,
+    /// Path to write the exported skeleton and materials to
+    #[clap(short = 'o', long)]
+    output_path: String,
+    /// Format to export to (gltf)
+    #[clap(short = 'f', long, default_value = "gltf")]
+    format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ExportFormat {
+    Gltf,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "gltf" => Ok(ExportFormat::Gltf),
+            _ => anyhow::bail!("{} is not a known export format (gltf)", s),
+        }
+    }
+}
+
+pub fn export_ape(opts: ExportOpts) -> anyhow::Result<()> {
+    let ape = ApeSource::new(&opts.input_path, opts.archive.as_deref()).read(None)?;
+
+    match opts.format {
+        ExportFormat::Gltf => {
+            let document = GltfDocument::from_ape(&ape);
+            let output_file = BufWriter::new(File::create(&opts.output_path)?);
+            serde_json::to_writer_pretty(output_file, &document)?;
+
+            println!(
+                "Exported {} bones and {} materials without geometry",
+                ape.bones.len(),
+                ape.materials.len()
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -1,24 +1,32 @@
 use clap::Parser;
-use fang::{ape::Ape, BinReaderExt};
-use std::{fs::File, io::BufReader};
+
+use super::ApeSource;
 
 #[derive(Parser, Debug)]
 pub struct InfoOpts {
-    /// Path to Ape
+    /// Path to Ape, or archive.mst:model.ape to read it from an archive
     #[clap(short = 'i', long)]
     input_path: String,
-    /// Whether or not the ape is for GameCube (big endian)
-    #[clap(short = 'g', long)]
+    /// Path to an MST to read the Ape from, input path is then the entry name
+    #[clap(short = 'a', long)]
+    archive: Option<String>,
+    /// Read the ape as big endian (GameCube) instead of detecting the byte order
+    #[clap(short = 'g', long, conflicts_with = "little-endian")]
     gamecube: bool,
+    /// Read the ape as little endian instead of detecting the byte order
+    #[clap(short = 'l', long)]
+    little_endian: bool,
 }
 
 pub fn info_ape(opts: InfoOpts) -> anyhow::Result<()> {
-    let mut file = BufReader::new(File::open(&opts.input_path)?);
+    let source = ApeSource::new(&opts.input_path, opts.archive.as_deref());
 
-    let ape = match opts.gamecube {
-        false => file.read_le::<Ape>()?,
-        true => file.read_be::<Ape>()?,
+    let is_little = match (opts.gamecube, opts.little_endian) {
+        (true, _) => Some(false),
+        (_, true) => Some(true),
+        _ => None,
     };
+    let ape = source.read(is_little)?;
     println!("{:#?}", &ape);
 
     Ok(())
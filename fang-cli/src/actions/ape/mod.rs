@@ -1,8 +1,23 @@
 use clap::Parser;
 
+mod source;
+pub use source::*;
+
 mod info;
 pub use info::*;
 
+mod export;
+pub use export::*;
+
+mod edit;
+pub use edit::*;
+
+mod summary;
+pub use summary::*;
+
+mod skeleton;
+pub use skeleton::*;
+
 /// Ape subcommand to run
 #[derive(Parser)]
 #[clap(about)]
@@ -10,12 +25,28 @@ pub enum Command {
     /// Parse and display data in Ape
     #[clap(about)]
     Info(InfoOpts),
+    /// Export the skeleton and materials of Ape as glTF, the geometry isn't exported
+    #[clap(about)]
+    Export(ExportOpts),
+    /// Change properties of Ape with a JSON patch file
+    #[clap(about)]
+    Edit(EditOpts),
+    /// Summarize every Ape in an MST as a table
+    #[clap(about)]
+    Summary(SummaryOpts),
+    /// Print and check the skeleton of Ape
+    #[clap(about)]
+    Skeleton(SkeletonOpts),
 }
 
 impl Command {
     pub fn process(self) -> anyhow::Result<()> {
         match self {
             Command::Info(opts) => info::info_ape(opts),
+            Command::Export(opts) => export::export_ape(opts),
+            Command::Edit(opts) => edit::edit_ape(opts),
+            Command::Summary(opts) => summary::summary_ape(opts),
+            Command::Skeleton(opts) => skeleton::skeleton_ape(opts),
         }
     }
 }
@@ -0,0 +1,56 @@
+use clap::Parser;
+
+use super::ApeSource;
+
+#[derive(Parser, Debug)]
+pub struct SkeletonOpts {
+    /// Path to Ape, or archive.mst:model.ape to read it from an archive
+    #[clap(short = 'i', long)]
+    input_path: String,
+    /// Path to an MST to read the Ape from, input path is then the entry name
+    #[clap(short = 'a', long)]
+    archive: Option<String>,
+    /// Also write the skeleton in its rest pose to a BVH file
+    #[clap(long)]
+    bvh: Option<String>,
+}
+
+pub fn skeleton_ape(opts: SkeletonOpts) -> anyhow::Result<()> {
+    let ape = ApeSource::new(&opts.input_path, opts.archive.as_deref()).read(None)?;
+
+    println!("Skeleton of {}: ({} bones)", ape.name, ape.bones.len());
+    for (bone_index, depth) in ape.bone_tree() {
+        let bone = &ape.bones[bone_index];
+        let offset = &bone.at_rest_bone_to_parent_mtx.p;
+        println!(
+            "{}{: <3} {: <32}  offset: ({}, {}, {})",
+            "  ".repeat(depth + 1),
+            bone_index,
+            bone.name,
+            offset.x,
+            offset.y,
+            offset.z
+        );
+    }
+
+    if let Some(bvh_path) = &opts.bvh {
+        std::fs::write(bvh_path, ape.skeleton_to_bvh())?;
+    }
+
+    let issues = ape.validate_skeleton();
+    if !issues.is_empty() {
+        println!();
+        for issue in &issues {
+            println!("{}", issue);
+        }
+        anyhow::bail!(
+            "found {} problems in {} bones",
+            issues.len(),
+            ape.bones.len()
+        );
+    }
+
+    println!("\nNo problems found in {} bones", ape.bones.len());
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use fang::ape::Ape;
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use crate::actions::mst::open_archive;
+
+/// Where to read an ape from, either a file or an entry in an archive
+pub enum ApeSource<'a> {
+    File(&'a str),
+    Archive {
+        archive_path: &'a str,
+        entry: &'a str,
+    },
+}
+
+impl<'a> ApeSource<'a> {
+    /// Read `input_path` as an entry of `archive_path` if given, otherwise from `archive.mst:model.ape`
+    /// if it looks like one, otherwise as a file
+    pub fn new(input_path: &'a str, archive_path: Option<&'a str>) -> Self {
+        if let Some(archive_path) = archive_path {
+            return ApeSource::Archive {
+                archive_path,
+                entry: input_path,
+            };
+        }
+
+        // Files with a colon in their name take precedence, like drive letters on Windows
+        match input_path.rsplit_once(':') {
+            Some((archive_path, entry))
+                if archive_path.to_ascii_lowercase().ends_with(".mst")
+                    && !Path::new(input_path).exists() =>
+            {
+                ApeSource::Archive {
+                    archive_path,
+                    entry,
+                }
+            }
+            _ => ApeSource::File(input_path),
+        }
+    }
+
+    /// Read the ape, `is_little` forces a byte order instead of detecting it or taking it from the archive
+    pub fn read(&self, is_little: Option<bool>) -> anyhow::Result<Ape> {
+        match self {
+            ApeSource::File(path) => {
+                let mut file = BufReader::new(File::open(path)?);
+                match is_little {
+                    Some(is_little) => Ok(Ape::read_endian(&mut file, is_little)?),
+                    None => Ok(Ape::read_auto(&mut file)?),
+                }
+            }
+            ApeSource::Archive {
+                archive_path,
+                entry,
+            } => {
+                let mut archive = open_archive(archive_path, false)?;
+                let is_little = is_little.unwrap_or_else(|| archive.identifier().is_little());
+                let mut entry_reader = archive.open_entry(entry)?;
+                Ok(Ape::read_endian(&mut entry_reader, is_little)?)
+            }
+        }
+    }
+
+    /// Read the raw data of the ape, along with the byte order of the archive it's in
+    pub fn read_data(&self) -> anyhow::Result<(Vec<u8>, Option<bool>)> {
+        match self {
+            ApeSource::File(path) => Ok((std::fs::read(path)?, None)),
+            ApeSource::Archive {
+                archive_path,
+                entry,
+            } => {
+                let mut archive = open_archive(archive_path, false)?;
+                let is_little = archive.identifier().is_little();
+                let mut data = Vec::new();
+                archive.open_entry(entry)?.read_to_end(&mut data)?;
+                Ok((data, Some(is_little)))
+            }
+        }
+    }
+}
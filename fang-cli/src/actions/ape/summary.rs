@@ -0,0 +1,146 @@
+use clap::Parser;
+use fang::{ape::Ape, mst::entry::Entry};
+
+use crate::actions::{
+    format::{print_csv_row, OutputFormat},
+    mst::{open_archive, FilterOpts},
+};
+
+#[derive(Parser, Debug)]
+pub struct SummaryOpts {
+    /// Path to MST
+    #[clap(short = 'i', long)]
+    input_path: String,
+    /// Output format (text, json, csv)
+    #[clap(short = 'f', long, default_value = "text")]
+    format: OutputFormat,
+    /// Guess the entry layout of archives with an unknown version
+    #[clap(long)]
+    lenient: bool,
+    #[clap(flatten)]
+    filter: FilterOpts,
+}
+
+/// The summarized properties of one ape in the archive
+struct ApeSummary {
+    filename: String,
+    result: anyhow::Result<Ape>,
+}
+
+pub fn summary_ape(opts: SummaryOpts) -> anyhow::Result<()> {
+    let mut archive = open_archive(&opts.input_path, opts.lenient)?;
+    let is_little = archive.identifier().is_little();
+
+    let filter = opts.filter.to_filter()?;
+    let selected = archive
+        .entries()
+        .enumerate()
+        .filter(|(_, e)| e.filename().to_ascii_lowercase().ends_with(".ape") && filter.matches(e))
+        .map(|(index, e)| (index, e.filename()))
+        .collect::<Vec<_>>();
+
+    // An ape that can't be read, like one cut off in a stripped archive, shouldn't stop the others
+    let summaries = selected
+        .into_iter()
+        .map(|(index, filename)| {
+            let result = archive
+                .open_entry_at(index)
+                .and_then(|mut entry_reader| Ape::read_endian(&mut entry_reader, is_little))
+                .map_err(anyhow::Error::from);
+            ApeSummary { filename, result }
+        })
+        .collect::<Vec<_>>();
+
+    match opts.format {
+        OutputFormat::Text => print_summary_text(&summaries),
+        OutputFormat::Json => print_summary_json(&summaries)?,
+        OutputFormat::Csv => print_summary_csv(&summaries),
+    }
+
+    Ok(())
+}
+
+fn print_summary_text(summaries: &[ApeSummary]) {
+    println!(
+        "{: <20}  {: <16}  {: >5}  {: >8}  {: >9}  {: >4}  {: >6}  {: >10}",
+        "Entry", "Name", "Bones", "Segments", "Materials", "LODs", "Lights", "Radius"
+    );
+
+    for summary in summaries {
+        match &summary.result {
+            Ok(ape) => println!(
+                "{: <20}  {: <16}  {: >5}  {: >8}  {: >9}  {: >4}  {: >6}  {: >10.2}",
+                summary.filename,
+                ape.name,
+                ape.bone_count,
+                ape.segment_count,
+                ape.material_count,
+                ape.lod_count,
+                ape.light_count,
+                ape.bound_sphere.radius
+            ),
+            Err(e) => println!("{: <20}  failed to read: {}", summary.filename, e),
+        }
+    }
+}
+
+fn print_summary_json(summaries: &[ApeSummary]) -> anyhow::Result<()> {
+    let summaries = summaries
+        .iter()
+        .map(|summary| match &summary.result {
+            Ok(ape) => serde_json::json!({
+                "entry": summary.filename,
+                "name": ape.name,
+                "bone_count": ape.bone_count,
+                "segment_count": ape.segment_count,
+                "material_count": ape.material_count,
+                "lod_count": ape.lod_count,
+                "light_count": ape.light_count,
+                "bound_radius": ape.bound_sphere.radius,
+            }),
+            Err(e) => serde_json::json!({
+                "entry": summary.filename,
+                "error": e.to_string(),
+            }),
+        })
+        .collect::<Vec<_>>();
+
+    println!("{}", serde_json::to_string_pretty(&summaries)?);
+    Ok(())
+}
+
+fn print_summary_csv(summaries: &[ApeSummary]) {
+    print_csv_row(&[
+        "entry",
+        "name",
+        "bone_count",
+        "segment_count",
+        "material_count",
+        "lod_count",
+        "light_count",
+        "bound_radius",
+        "error",
+    ]);
+
+    for summary in summaries {
+        match &summary.result {
+            Ok(ape) => print_csv_row(&[
+                summary.filename.clone(),
+                ape.name.to_string(),
+                ape.bone_count.to_string(),
+                ape.segment_count.to_string(),
+                ape.material_count.to_string(),
+                ape.lod_count.to_string(),
+                ape.light_count.to_string(),
+                ape.bound_sphere.radius.to_string(),
+                String::new(),
+            ]),
+            Err(e) => {
+                let mut row = vec![String::new(); 9];
+                row[0] = summary.filename.clone();
+                row[8] = e.to_string();
+                print_csv_row(&row);
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::str::FromStr;
+
+/// How a command prints its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable text
+    Text,
+    /// JSON, for other tools to consume
+    Json,
+    /// Comma separated values with a header row
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => anyhow::bail!("{} is not a known output format (text, json, csv)", s),
+        }
+    }
+}
+
+/// Print a row of comma separated values, quoting values that need it
+pub fn print_csv_row<T: ToString>(values: &[T]) {
+    let row = values
+        .iter()
+        .map(|value| {
+            let value = value.to_string();
+            if value.contains(&[',', '"', '\n', '\r'][..]) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value
+            }
+        })
+        .collect::<Vec<_>>();
+
+    println!("{}", row.join(","));
+}
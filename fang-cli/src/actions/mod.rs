@@ -1,6 +1,7 @@
 use clap::Parser;
 
 mod ape;
+mod format;
 mod mst;
 mod rdg;
 
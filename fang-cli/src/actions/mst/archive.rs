@@ -0,0 +1,37 @@
+use fang::mst::archive::MstArchive;
+use std::{fs::File, io::BufReader};
+
+/// Open an archive for reading, guessing its entry layout in lenient mode and reporting the guess
+pub fn open_archive(
+    input_path: &str,
+    lenient: bool,
+) -> anyhow::Result<MstArchive<BufReader<File>>> {
+    let reader = BufReader::new(File::open(input_path)?);
+    if !lenient {
+        return Ok(MstArchive::new(reader)?);
+    }
+
+    let archive = MstArchive::new_lenient(reader)?;
+    if let Some(guess) = archive.layout_guess() {
+        let version = archive.mst().body.version();
+        eprintln!(
+            "Mst version {}.{}.{} is not fully known, reading it as {:?} for {:?}",
+            version.major(),
+            version.minor(),
+            version.patch(),
+            guess.layout,
+            guess.platform
+        );
+        eprintln!(
+            "{} of {} entries look valid, the entry tables {} the data offset",
+            guess.plausible_entries,
+            guess.total_entries,
+            match guess.fits_data_offset {
+                true => "line up with",
+                false => "don't line up with",
+            }
+        );
+    }
+
+    Ok(archive)
+}
@@ -1,6 +1,9 @@
 use clap::Parser;
 use fang::{
-    mst::{builder::MstBuilder, entry::Entry, Mst},
+    mst::{
+        builder::{MstBuilder, MstBuilderOrder},
+        Mst,
+    },
     BinReaderExt,
 };
 use std::{
@@ -20,6 +23,9 @@ pub struct CombineOpts {
     /// Path to output MST
     #[clap(short = 'o', long)]
     output_path: Option<String>,
+    /// Order of the entries in the output (insertion, sorted, source)
+    #[clap(long, default_value = "insertion")]
+    order: MstBuilderOrder,
 }
 
 pub fn combine_mst(opts: CombineOpts) -> anyhow::Result<()> {
@@ -33,28 +39,24 @@ pub fn combine_mst(opts: CombineOpts) -> anyhow::Result<()> {
 
     // Prepare a new Mst, copying the versions and platform from input1
     let mut mst_builder = MstBuilder::from_mst_empty(&mst1)?;
+    mst_builder.set_order(opts.order);
 
-    // Add all the entries from the first source Mst as references
-    for entry in mst1.collect_entries() {
-        mst_builder.add_entry_file(
-            entry.filename().to_string(),
-            opts.input1_path.clone(),
-            entry.offset(),
-            entry.size(),
-            Some(entry.timestamp().timestamp() as u32),
-        );
-    }
+    // Add all the entries from both source Msts as references
+    mst_builder.add_mst_entries(&mst1, &opts.input1_path);
+    mst_builder.add_mst_entries(&mst2, &opts.input2_path);
 
-    // Add all the entries from the second source Mst as references
-    for entry in mst2.collect_entries() {
-        mst_builder.add_entry_file(
-            entry.filename().to_string(),
-            opts.input2_path.clone(),
-            entry.offset(),
-            entry.size(),
-            Some(entry.timestamp().timestamp() as u32),
-        );
-    }
+    // Add the support entries from both source Msts, the second one taking precedence
+    mst_builder.add_mst_support_entries(&mst1);
+    mst_builder.add_mst_support_entries(&mst2);
+
+    // Keep the total number of slots of the first source Mst, with the combined entries taking up free ones
+    let header = mst1.body.header;
+    mst_builder.set_free_slots(
+        ((header.num_entries + header.num_free_entries) as usize)
+            .saturating_sub(mst_builder.entry_count()),
+        ((header.num_support_entries + header.num_free_support_entries) as usize)
+            .saturating_sub(mst_builder.support_entry_count()),
+    );
 
     // Finalize and write the Mst with context to specified output path or input1_path.combined.mst
     let out_path = match opts.output_path {
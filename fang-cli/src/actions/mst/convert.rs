@@ -1,6 +1,9 @@
 use clap::Parser;
 use fang::{
-    mst::{builder::MstBuilder, entry::Entry, Mst, MstVersionKnown},
+    mst::{
+        builder::{MstBuilder, MstBuilderOrder},
+        Mst, MstVersionKnown,
+    },
     BinReaderExt,
 };
 use std::{
@@ -20,6 +23,9 @@ pub struct ConvertOpts {
     /// New minor version
     #[clap(long)]
     minor: Option<u8>,
+    /// Order of the entries in the output (insertion, sorted, source)
+    #[clap(long, default_value = "insertion")]
+    order: MstBuilderOrder,
 }
 
 pub fn convert_mst(opts: ConvertOpts) -> anyhow::Result<()> {
@@ -29,6 +35,7 @@ pub fn convert_mst(opts: ConvertOpts) -> anyhow::Result<()> {
 
     // Prepare a new Mst, copying the versions and platform
     let mut mst_builder = MstBuilder::from_mst_empty(&mst)?;
+    mst_builder.set_order(opts.order);
 
     // Update the Mst version with given minor version (6, 7, 8)
     let mut new_mst_version = *mst.body.version();
@@ -37,16 +44,13 @@ pub fn convert_mst(opts: ConvertOpts) -> anyhow::Result<()> {
 
     mst_builder.set_version(&new_version);
 
-    // Add all the entries from the source Mst as references
-    for entry in mst.collect_entries() {
-        mst_builder.add_entry_file(
-            entry.filename().to_string(),
-            opts.input_path.clone(),
-            entry.offset(),
-            entry.size(),
-            Some(entry.timestamp().timestamp() as u32),
-        );
-    }
+    // Add all the entries from the source Mst as references, and carry over the support entries and the free slots
+    mst_builder.add_mst_entries(&mst, &opts.input_path);
+    mst_builder.add_mst_support_entries(&mst);
+    mst_builder.set_free_slots(
+        mst.body.header.num_free_entries as usize,
+        mst.body.header.num_free_support_entries as usize,
+    );
 
     // Finalize and write the Mst with contents to specified output path or input_path.convert.mst
     let out_path = match opts.output_path {
@@ -0,0 +1,118 @@
+use clap::Parser;
+use fang::{
+    mst::{
+        archive::MstArchive,
+        diff::{MstContentChange, MstDiff},
+        entry::Entry,
+        Mst,
+    },
+    BinReaderExt,
+};
+use std::{fs::File, io::BufReader};
+
+use crate::actions::format::OutputFormat;
+
+#[derive(Parser, Debug)]
+pub struct DiffOpts {
+    /// Path to the old MST
+    #[clap(short = 'a', long)]
+    old_path: String,
+    /// Path to the new MST
+    #[clap(short = 'b', long)]
+    new_path: String,
+    /// Only compare the entry tables, detecting content changes from checksums
+    #[clap(long)]
+    no_data: bool,
+    /// Output format (text, json)
+    #[clap(short = 'f', long, default_value = "text")]
+    format: OutputFormat,
+}
+
+pub fn diff_mst(opts: DiffOpts) -> anyhow::Result<()> {
+    let diff = if opts.no_data {
+        let old: Mst = BufReader::new(File::open(&opts.old_path)?).read_le()?;
+        let new: Mst = BufReader::new(File::open(&opts.new_path)?).read_le()?;
+        MstDiff::from_tables(&old, &new)?
+    } else {
+        let mut old = MstArchive::new(BufReader::new(File::open(&opts.old_path)?))?;
+        let mut new = MstArchive::new(BufReader::new(File::open(&opts.new_path)?))?;
+        MstDiff::from_archives(&mut old, &mut new)?
+    };
+
+    match opts.format {
+        OutputFormat::Text => print_diff(&diff),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        OutputFormat::Csv => anyhow::bail!("diff can only be printed as text or json"),
+    }
+
+    Ok(())
+}
+
+fn print_diff(diff: &MstDiff) {
+    if diff.is_empty() {
+        println!("No differences");
+        return;
+    }
+
+    if !diff.header.is_empty() {
+        println!("Header:");
+        for change in &diff.header {
+            println!(
+                " {: <26}  {:#010x} -> {:#010x}  ({} -> {})",
+                change.field, change.old, change.new, change.old, change.new
+            );
+        }
+    }
+
+    if !diff.added.is_empty() {
+        println!("Added: ({} entries)", diff.added.len());
+        for entry in &diff.added {
+            println!(
+                " {: <20}  size: {: <10}  modified: {}",
+                entry.filename(),
+                entry.size(),
+                entry.timestamp()
+            );
+        }
+    }
+
+    if !diff.removed.is_empty() {
+        println!("Removed: ({} entries)", diff.removed.len());
+        for entry in &diff.removed {
+            println!(
+                " {: <20}  size: {: <10}  modified: {}",
+                entry.filename(),
+                entry.size(),
+                entry.timestamp()
+            );
+        }
+    }
+
+    if !diff.changed.is_empty() {
+        println!("Changed: ({} entries)", diff.changed.len());
+        for change in &diff.changed {
+            let mut details = Vec::new();
+            if change.resized {
+                details.push(format!(
+                    "size: {} -> {}",
+                    change.old.size(),
+                    change.new.size()
+                ));
+            }
+            if change.retimestamped {
+                details.push(format!(
+                    "modified: {} -> {}",
+                    change.old.timestamp(),
+                    change.new.timestamp()
+                ));
+            }
+            match change.content {
+                MstContentChange::Changed => details.push("content changed".to_string()),
+                MstContentChange::Unknown => details.push("content unknown".to_string()),
+                MstContentChange::Unchanged => {}
+            }
+
+            println!(" {: <20}  {}", change.new.filename(), details.join("  "));
+        }
+    }
+}
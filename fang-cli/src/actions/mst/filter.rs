@@ -0,0 +1,70 @@
+use clap::Parser;
+use fang::{mst::filter::EntryFilter, util::parse_epoch};
+
+/// Options to select a subset of the entries in an archive
+#[derive(Parser, Debug)]
+pub struct FilterOpts {
+    /// Only entries matching this glob, like *.ape, can be repeated
+    #[clap(long)]
+    include: Vec<String>,
+    /// Skip entries matching this glob, can be repeated
+    #[clap(long)]
+    exclude: Vec<String>,
+    /// Only entries matching this regex, can be repeated
+    #[clap(long)]
+    include_regex: Vec<String>,
+    /// Skip entries matching this regex, can be repeated
+    #[clap(long)]
+    exclude_regex: Vec<String>,
+    /// Only entries with this extension, can be repeated
+    #[clap(long = "ext")]
+    extensions: Vec<String>,
+    /// Only entries of at least this many bytes
+    #[clap(long)]
+    min_size: Option<u32>,
+    /// Only entries of at most this many bytes
+    #[clap(long)]
+    max_size: Option<u32>,
+    /// Only entries modified at or after this time (epoch seconds, YYYY-MM-DD or RFC 3339)
+    #[clap(long)]
+    modified_after: Option<String>,
+    /// Only entries modified at or before this time (epoch seconds, YYYY-MM-DD or RFC 3339)
+    #[clap(long)]
+    modified_before: Option<String>,
+}
+
+impl FilterOpts {
+    pub fn to_filter(&self) -> anyhow::Result<EntryFilter> {
+        let mut filter = EntryFilter::new();
+
+        for pattern in &self.include {
+            filter.add_include_glob(pattern)?;
+        }
+        for pattern in &self.exclude {
+            filter.add_exclude_glob(pattern)?;
+        }
+        for pattern in &self.include_regex {
+            filter.add_include_regex(pattern)?;
+        }
+        for pattern in &self.exclude_regex {
+            filter.add_exclude_regex(pattern)?;
+        }
+        for extension in &self.extensions {
+            filter.add_extension(extension);
+        }
+
+        filter.set_size_range(self.min_size, self.max_size);
+        filter.set_timestamp_range(
+            self.modified_after
+                .as_deref()
+                .map(parse_epoch)
+                .transpose()?,
+            self.modified_before
+                .as_deref()
+                .map(parse_epoch)
+                .transpose()?,
+        );
+
+        Ok(filter)
+    }
+}
@@ -5,11 +5,16 @@ use fang::{
 };
 use std::{fs::File, io::BufReader};
 
+use crate::actions::format::{print_csv_row, OutputFormat};
+
 #[derive(Parser, Debug)]
 pub struct InfoOpts {
     /// Path to MST
     #[clap(short = 'i', long)]
     input_path: String,
+    /// Output format (text, json, csv)
+    #[clap(short = 'f', long, default_value = "text")]
+    format: OutputFormat,
 }
 
 pub fn info_mst(opts: InfoOpts) -> anyhow::Result<()> {
@@ -18,6 +23,16 @@ pub fn info_mst(opts: InfoOpts) -> anyhow::Result<()> {
     let mst = file.read_le::<Mst>()?;
     // eprintln!("{:#?}", &mst);
 
+    match opts.format {
+        OutputFormat::Text => print_info_text(&mst),
+        OutputFormat::Json => print_info_json(&mst)?,
+        OutputFormat::Csv => print_info_csv(&mst),
+    }
+
+    Ok(())
+}
+
+fn print_info_text(mst: &Mst) {
     println!(
         "Version: {}.{}.{} ({:?})",
         mst.body.version().major(),
@@ -87,5 +102,65 @@ pub fn info_mst(opts: InfoOpts) -> anyhow::Result<()> {
         mst.body.header.compilers.cam_compiler_version
     );
 
+    println!("\nReserved: {:x?}", mst.body.header.reserved);
+}
+
+fn print_info_json(mst: &Mst) -> anyhow::Result<()> {
+    let entries = mst.collect_entries();
+
+    let info = serde_json::json!({
+        "identifier": mst.identifier,
+        "version": mst.body.version(),
+        "header": mst.body.header,
+        "oldest_entry": entries.iter().map(|e| e.timestamp).min(),
+        "newest_entry": entries.iter().map(|e| e.timestamp).max(),
+    });
+    println!("{}", serde_json::to_string_pretty(&info)?);
+
     Ok(())
 }
+
+fn print_info_csv(mst: &Mst) {
+    let version = mst.body.version();
+    let header = &mst.body.header;
+    let compilers = &header.compilers;
+
+    print_csv_row(&["field", "value"]);
+    print_csv_row(&[
+        "version".to_string(),
+        format!(
+            "{}.{}.{}",
+            version.major(),
+            version.minor(),
+            version.patch()
+        ),
+    ]);
+    for (field, value) in [
+        ("xbox", version.xbox() as u32),
+        ("pc", version.pc() as u32),
+        ("tools", version.tools() as u32),
+        ("gc", version.gc() as u32),
+        ("ps2", version.ps2() as u32),
+        ("bytes_in_file", header.bytes_in_file),
+        ("num_entries", header.num_entries),
+        ("num_free_entries", header.num_free_entries),
+        ("num_support_entries", header.num_support_entries),
+        ("num_free_support_entries", header.num_free_support_entries),
+        ("data_offset", header.data_offset),
+        ("tga_compiler_version", compilers.tga_compiler_version),
+        ("ape_compiler_version", compilers.ape_compiler_version),
+        ("mtx_compiler_version", compilers.mtx_compiler_version),
+        ("csv_compiler_version", compilers.csv_compiler_version),
+        ("fnt_compiler_version", compilers.fnt_compiler_version),
+        ("sma_compiler_version", compilers.sma_compiler_version),
+        ("gt_compiler_version", compilers.gt_compiler_version),
+        ("wvb_compiler_version", compilers.wvb_compiler_version),
+        ("fpr_compiler_version", compilers.fpr_compiler_version),
+        ("cam_compiler_version", compilers.cam_compiler_version),
+    ] {
+        print_csv_row(&[field.to_string(), value.to_string()]);
+    }
+    for (index, value) in header.reserved.iter().enumerate() {
+        print_csv_row(&[format!("reserved_{}", index), value.to_string()]);
+    }
+}
@@ -1,28 +1,52 @@
 use clap::Parser;
-use fang::{
-    mst::{
-        entry::{Entry, SupportEntry},
-        Mst,
-    },
-    BinReaderExt,
-};
-use std::{fs::File, io::BufReader};
+use fang::mst::entry::{CanonicalEntry, CanonicalSupportEntry, Entry, SupportEntry};
+
+use super::{open_archive, FilterOpts};
+use crate::actions::format::{print_csv_row, OutputFormat};
 
 #[derive(Parser, Debug)]
 pub struct ListOpts {
     /// Path to MST
     #[clap(short = 'i', long)]
     input_path: String,
+    /// Output format (text, json, csv)
+    #[clap(short = 'f', long, default_value = "text")]
+    format: OutputFormat,
+    /// Guess the entry layout of archives with an unknown version
+    #[clap(long)]
+    lenient: bool,
+    #[clap(flatten)]
+    filter: FilterOpts,
 }
 
 pub fn list_mst(opts: ListOpts) -> anyhow::Result<()> {
-    let mut file = BufReader::new(File::open(&opts.input_path)?);
+    let archive = open_archive(&opts.input_path, opts.lenient)?;
+
+    let filter = opts.filter.to_filter()?;
+    let entries = archive
+        .entries()
+        .filter(|e| filter.matches(e))
+        .cloned()
+        .collect::<Vec<_>>();
+    let support_entries = archive
+        .support_entries()
+        .filter(|e| filter.matches_support(e))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    match opts.format {
+        OutputFormat::Text => print_list_text(&entries, &support_entries),
+        OutputFormat::Json => print_list_json(&entries, &support_entries)?,
+        OutputFormat::Csv => print_list_csv(&entries, &support_entries),
+    }
 
-    let mst = file.read_le::<Mst>()?;
+    Ok(())
+}
 
-    println!("MST Entries: ({} entries)", mst.body.header.num_entries);
+fn print_list_text(entries: &[CanonicalEntry], support_entries: &[CanonicalSupportEntry]) {
+    println!("MST Entries: ({} entries)", entries.len());
 
-    for entry in mst.collect_entries() {
+    for entry in entries {
         println!(
             "{: <20}  pos: {: <10}  size: {: <10}  modified: {}",
             entry.filename(),
@@ -32,17 +56,62 @@ pub fn list_mst(opts: ListOpts) -> anyhow::Result<()> {
         );
     }
 
-    println!(
-        "MST Support Entries: ({} entries)",
-        mst.body.header.num_support_entries
-    );
-    for support_entry in mst.collect_support_entries() {
+    println!("MST Support Entries: ({} entries)", support_entries.len());
+    for support_entry in support_entries {
         println!(
             "{: <20}  modified: {}",
             support_entry.filename(),
             support_entry.timestamp()
         );
     }
+}
+
+fn print_list_json(
+    entries: &[CanonicalEntry],
+    support_entries: &[CanonicalSupportEntry],
+) -> anyhow::Result<()> {
+    let list = serde_json::json!({
+        "entries": entries,
+        "support_entries": support_entries,
+    });
+    println!("{}", serde_json::to_string_pretty(&list)?);
 
     Ok(())
 }
+
+/// One row per entry, support entries have no data so their offset, size, flags and crc are left empty
+fn print_list_csv(entries: &[CanonicalEntry], support_entries: &[CanonicalSupportEntry]) {
+    print_csv_row(&[
+        "kind",
+        "filename",
+        "offset",
+        "size",
+        "timestamp",
+        "flags",
+        "crc",
+    ]);
+
+    for entry in entries {
+        print_csv_row(&[
+            "entry".to_string(),
+            entry.filename(),
+            entry.offset.to_string(),
+            entry.size.to_string(),
+            entry.timestamp.to_string(),
+            entry.flags.to_string(),
+            format!("{:08x}", entry.crc),
+        ]);
+    }
+
+    for support_entry in support_entries {
+        print_csv_row(&[
+            "support".to_string(),
+            support_entry.filename(),
+            String::new(),
+            String::new(),
+            support_entry.timestamp.to_string(),
+            String::new(),
+            String::new(),
+        ]);
+    }
+}
@@ -1,5 +1,11 @@
 use clap::Parser;
 
+mod archive;
+pub use archive::*;
+
+mod filter;
+pub use filter::*;
+
 mod info;
 pub use info::*;
 
@@ -18,6 +24,21 @@ pub use combine::*;
 mod strip;
 pub use strip::*;
 
+mod pack;
+pub use pack::*;
+
+mod repack;
+pub use repack::*;
+
+mod verify;
+pub use verify::*;
+
+mod patch;
+pub use patch::*;
+
+mod diff;
+pub use diff::*;
+
 /// MST subcommand to run
 #[derive(Parser)]
 #[clap(about)]
@@ -40,6 +61,21 @@ pub enum Command {
     /// Read the file and write it back without the content
     #[clap(about)]
     Strip(StripOpts),
+    /// Build a new archive from a directory of files
+    #[clap(about)]
+    Pack(PackOpts),
+    /// Rebuild an unpacked archive from its manifest
+    #[clap(about)]
+    Repack(RepackOpts),
+    /// Check entry checksums and data ranges
+    #[clap(about)]
+    Verify(VerifyOpts),
+    /// Replace, add or delete entries without rebuilding the archive
+    #[clap(about)]
+    Patch(PatchOpts),
+    /// Compare the headers and entries of two archives
+    #[clap(about)]
+    Diff(DiffOpts),
 }
 
 impl Command {
@@ -51,6 +87,11 @@ impl Command {
             Command::Convert(opts) => convert::convert_mst(opts),
             Command::Combine(opts) => combine::combine_mst(opts),
             Command::Strip(opts) => strip::strip_mst(opts),
+            Command::Pack(opts) => pack::pack_mst(opts),
+            Command::Repack(opts) => repack::repack_mst(opts),
+            Command::Verify(opts) => verify::verify_mst(opts),
+            Command::Patch(opts) => patch::patch_mst(opts),
+            Command::Diff(opts) => diff::diff_mst(opts),
         }
     }
 }
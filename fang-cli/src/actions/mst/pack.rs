@@ -0,0 +1,144 @@
+use clap::Parser;
+use fang::mst::{
+    builder::{MstBuilder, MstBuilderOrder},
+    header::MstCompilers,
+    MstPlatformKnown, MstVersionKnown,
+};
+use std::{collections::HashMap, fs::File, io::BufWriter, time::UNIX_EPOCH};
+
+use super::MANIFEST_FILENAME;
+
+#[derive(Parser, Debug)]
+pub struct PackOpts {
+    /// Directory containing the files to pack
+    #[clap(short = 'i', long)]
+    input_dir: String,
+    /// Path to output MST
+    #[clap(short = 'o', long)]
+    output_path: String,
+    /// Target minor version (6, 7, 8)
+    #[clap(long, default_value = "8")]
+    minor: u8,
+    /// Target platform (xbox, pc, gc, ps2)
+    #[clap(short = 'p', long)]
+    platform: MstPlatformKnown,
+    /// Order of the entries in the output (insertion, sorted, source)
+    #[clap(long, default_value = "insertion")]
+    order: MstBuilderOrder,
+    /// Number of free entry slots to leave for files added later on
+    #[clap(long, default_value = "0")]
+    free_entries: usize,
+    /// Number of free support entry slots to leave for files added later on
+    #[clap(long, default_value = "0")]
+    free_support_entries: usize,
+    /// Write CRC-32 checksums of the entries' data, the engine's checksum algorithm is not confirmed yet
+    #[clap(long)]
+    write_crcs: bool,
+    /// TGA compiler version
+    #[clap(long, default_value = "0")]
+    tga_compiler: u32,
+    /// APE compiler version
+    #[clap(long, default_value = "0")]
+    ape_compiler: u32,
+    /// MTX compiler version
+    #[clap(long, default_value = "0")]
+    mtx_compiler: u32,
+    /// CSV compiler version
+    #[clap(long, default_value = "0")]
+    csv_compiler: u32,
+    /// FNT compiler version
+    #[clap(long, default_value = "0")]
+    fnt_compiler: u32,
+    /// SMA compiler version
+    #[clap(long, default_value = "0")]
+    sma_compiler: u32,
+    /// GT compiler version
+    #[clap(long, default_value = "0")]
+    gt_compiler: u32,
+    /// WVB compiler version
+    #[clap(long, default_value = "0")]
+    wvb_compiler: u32,
+    /// FPR compiler version
+    #[clap(long, default_value = "0")]
+    fpr_compiler: u32,
+    /// CAM compiler version
+    #[clap(long, default_value = "0")]
+    cam_compiler: u32,
+}
+
+pub fn pack_mst(opts: PackOpts) -> anyhow::Result<()> {
+    let version = MstVersionKnown::from_minor(opts.minor, opts.platform)?;
+
+    let compilers = MstCompilers {
+        tga_compiler_version: opts.tga_compiler,
+        ape_compiler_version: opts.ape_compiler,
+        mtx_compiler_version: opts.mtx_compiler,
+        csv_compiler_version: opts.csv_compiler,
+        fnt_compiler_version: opts.fnt_compiler,
+        sma_compiler_version: opts.sma_compiler,
+        gt_compiler_version: opts.gt_compiler,
+        wvb_compiler_version: opts.wvb_compiler,
+        fpr_compiler_version: opts.fpr_compiler,
+        cam_compiler_version: opts.cam_compiler,
+    };
+
+    let mut mst_builder = MstBuilder::new(version, opts.platform, compilers);
+    mst_builder.set_order(opts.order);
+    mst_builder.set_free_slots(opts.free_entries, opts.free_support_entries);
+    mst_builder.set_write_crcs(opts.write_crcs);
+
+    // Collect the files in the input directory, archives have no notion of subdirectories
+    let mut file_paths = Vec::new();
+    for dir_entry in std::fs::read_dir(&opts.input_dir)? {
+        let dir_entry = dir_entry?;
+        if dir_entry.file_type()?.is_file() && dir_entry.file_name() != MANIFEST_FILENAME {
+            file_paths.push(dir_entry.path());
+        }
+    }
+    file_paths.sort();
+
+    // Add every file as a reference, using its modification time as the entry timestamp
+    let mut used_names = HashMap::new();
+    for file_path in file_paths {
+        let entry_path = match file_path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.is_ascii() => name.to_string(),
+            _ => anyhow::bail!("{} is not a valid ascii entry name", file_path.display()),
+        };
+
+        if entry_path.len() > version.max_filename_len() {
+            return Err(fang::Error::FilenameTooLong {
+                name: entry_path,
+                version,
+            }
+            .into());
+        }
+
+        // Entries are looked up ignoring case, so names that only differ in case can't both be reached
+        if let Some(other_path) =
+            used_names.insert(entry_path.to_ascii_uppercase(), entry_path.clone())
+        {
+            anyhow::bail!(
+                "{} and {} only differ in case, the engine can't tell them apart",
+                other_path,
+                entry_path
+            );
+        }
+
+        let metadata = file_path.metadata()?;
+        let timestamp = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_secs() as u32;
+
+        mst_builder.add_entry_file(
+            entry_path,
+            file_path.to_string_lossy().to_string(),
+            0,
+            metadata.len() as usize,
+            Some(timestamp),
+        );
+    }
+
+    let mut out_file = BufWriter::new(File::create(&opts.output_path)?);
+
+    mst_builder.write(&mut out_file)?;
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use clap::Parser;
+use fang::mst::patch::MstPatcher;
+use std::{fs::OpenOptions, path::Path, time::UNIX_EPOCH};
+
+#[derive(Parser, Debug)]
+pub struct PatchOpts {
+    /// Path to the MST to modify in place
+    #[clap(short = 'i', long)]
+    input_path: String,
+    /// File to replace the entry of the same name with, can be repeated
+    #[clap(short = 'r', long)]
+    replace: Vec<String>,
+    /// File to add as a new entry of the same name, can be repeated
+    #[clap(short = 'a', long)]
+    add: Vec<String>,
+    /// Name of an entry to delete, can be repeated
+    #[clap(short = 'd', long)]
+    delete: Vec<String>,
+    /// Write CRC-32 checksums of replaced and added data, the engine's checksum algorithm is not confirmed yet
+    #[clap(long)]
+    write_crcs: bool,
+}
+
+pub fn patch_mst(opts: PatchOpts) -> anyhow::Result<()> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&opts.input_path)?;
+
+    let mut patcher = MstPatcher::new(file)?;
+    patcher.set_write_crcs(opts.write_crcs);
+
+    // Delete first so the freed slots can be used by added entries
+    for entry_name in &opts.delete {
+        patcher.delete_entry(entry_name)?;
+    }
+
+    for file_path in &opts.replace {
+        let (entry_name, data, timestamp) = read_entry_file(file_path)?;
+        patcher.replace_entry(&entry_name, &data, Some(timestamp))?;
+    }
+
+    for file_path in &opts.add {
+        let (entry_name, data, timestamp) = read_entry_file(file_path)?;
+        patcher.add_entry(&entry_name, &data, Some(timestamp))?;
+    }
+
+    patcher.finish()?;
+
+    Ok(())
+}
+
+/// Read a file to patch in, named after the file and using its modification time as the timestamp
+fn read_entry_file(file_path: &str) -> anyhow::Result<(String, Vec<u8>, u32)> {
+    let file_path = Path::new(file_path);
+    let entry_name = match file_path.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.is_ascii() => name.to_string(),
+        _ => anyhow::bail!("{} is not a valid ascii entry name", file_path.display()),
+    };
+
+    let data = std::fs::read(file_path)?;
+    let timestamp = file_path
+        .metadata()?
+        .modified()?
+        .duration_since(UNIX_EPOCH)?
+        .as_secs() as u32;
+
+    Ok((entry_name, data, timestamp))
+}
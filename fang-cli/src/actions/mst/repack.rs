@@ -0,0 +1,32 @@
+use clap::Parser;
+use fang::mst::manifest::MstManifest;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+/// Name of the manifest written next to the unpacked files
+pub const MANIFEST_FILENAME: &str = "mst_manifest.json";
+
+#[derive(Parser, Debug)]
+pub struct RepackOpts {
+    /// Directory created by unpack, containing the manifest and files
+    #[clap(short = 'i', long)]
+    input_dir: String,
+    /// Path to output MST
+    #[clap(short = 'o', long)]
+    output_path: String,
+}
+
+pub fn repack_mst(opts: RepackOpts) -> anyhow::Result<()> {
+    let input_dir = Path::new(&opts.input_dir);
+
+    let manifest_file = BufReader::new(File::open(input_dir.join(MANIFEST_FILENAME))?);
+    let manifest: MstManifest = serde_json::from_reader(manifest_file)?;
+
+    let mut out_file = BufWriter::new(File::create(&opts.output_path)?);
+    manifest.write(input_dir, &mut out_file)?;
+
+    Ok(())
+}
@@ -1,12 +1,13 @@
 use clap::Parser;
-use fang::{
-    mst::{entry::Entry, Mst},
-    BinReaderExt,
-};
-use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use fang::mst::{entry::Entry, manifest::MstManifest};
+use filetime::FileTime;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind};
 use std::path::Path;
 
+use super::{open_archive, FilterOpts, MANIFEST_FILENAME};
+
 #[derive(Parser, Debug)]
 pub struct UnpackOpts {
     /// Path to MST
@@ -15,24 +16,268 @@ pub struct UnpackOpts {
     /// Output directory
     #[clap(short = 'o', long)]
     output_dir: String,
+    /// Replace characters that aren't allowed in filenames instead of refusing to unpack
+    #[clap(long)]
+    sanitize_names: bool,
+    /// Overwrite files that already exist in the output directory
+    #[clap(long, conflicts_with = "skip-existing")]
+    overwrite: bool,
+    /// Leave files that already exist in the output directory as they are
+    #[clap(long)]
+    skip_existing: bool,
+    /// Guess the entry layout of archives with an unknown version
+    #[clap(long)]
+    lenient: bool,
+    #[clap(flatten)]
+    filter: FilterOpts,
 }
 
 pub fn unpack_mst(opts: UnpackOpts) -> anyhow::Result<()> {
-    let mut file = BufReader::new(File::open(&opts.input_path)?);
+    let mut archive = open_archive(&opts.input_path, opts.lenient)?;
+    let output_dir = Path::new(&opts.output_dir);
+
+    let filter = opts.filter.to_filter()?;
+    let selected = archive
+        .entries()
+        .enumerate()
+        .filter(|(_, e)| filter.matches(e))
+        .map(|(index, e)| (index, e.filename(), e.timestamp))
+        .collect::<Vec<_>>();
+
+    // Decide on every output path before writing anything, so a bad name doesn't leave a partial unpack
+    let mut used_names = HashSet::from([MANIFEST_FILENAME.to_ascii_uppercase()]);
+    let mut output_names = Vec::with_capacity(selected.len());
+    for (_, filename, _) in &selected {
+        let safe_name = if is_safe_filename(filename) {
+            filename.clone()
+        } else if opts.sanitize_names {
+            sanitize_filename(filename)
+        } else {
+            anyhow::bail!(
+                "{:?} is not a plain filename, use --sanitize-names to unpack it anyway",
+                filename
+            );
+        };
+
+        // Names that only differ in case would overwrite each other on most filesystems
+        let output_name = unique_filename(&safe_name, &mut used_names);
+        if output_name != *filename {
+            println!("Unpacking {:?} as {}", filename, output_name);
+        }
+        output_names.push(output_name);
+    }
 
-    let mst = file.read_le::<Mst>()?;
+    std::fs::create_dir_all(output_dir)?;
 
-    for entry in mst.collect_entries() {
-        file.seek(SeekFrom::Start(entry.offset() as u64))?;
-        let mut data = vec![0; entry.size()];
-        file.read_exact(&mut data)?;
+    for ((index, _, timestamp), output_name) in selected.iter().zip(&output_names) {
+        let output_path = output_dir.join(output_name);
 
-        std::fs::create_dir_all(&opts.output_dir)?;
+        let output_file = if opts.overwrite {
+            File::create(&output_path)?
+        } else {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&output_path)
+            {
+                Ok(output_file) => output_file,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && opts.skip_existing => continue,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => anyhow::bail!(
+                    "{} already exists, use --overwrite or --skip-existing",
+                    output_path.display()
+                ),
+                Err(e) => return Err(e.into()),
+            }
+        };
 
-        let mut output_file = File::create(Path::new(&opts.output_dir).join(&entry.filename()))?;
+        let mut entry_reader = archive.open_entry_at(*index)?;
+        let mut output_file = BufWriter::new(output_file);
+        std::io::copy(&mut entry_reader, &mut output_file)?;
+        drop(output_file);
 
-        output_file.write_all(&data)?;
+        filetime::set_file_mtime(&output_path, FileTime::from_unix_time(*timestamp as i64, 0))?;
     }
 
+    // A partial unpack can't be repacked, so only write the manifest when everything was unpacked
+    if selected.len() < archive.entries().len() {
+        println!(
+            "Unpacked {} of {} entries, skipping the manifest",
+            selected.len(),
+            archive.entries().len()
+        );
+        return Ok(());
+    }
+
+    // Record everything else about the archive so it can be repacked, pointing at the names actually written
+    let mut manifest = MstManifest::from_mst(archive.mst());
+    for (manifest_entry, output_name) in manifest.entries.iter_mut().zip(output_names) {
+        manifest_entry.path = output_name;
+    }
+
+    let manifest_file = File::create(output_dir.join(MANIFEST_FILENAME))?;
+    serde_json::to_writer_pretty(BufWriter::new(manifest_file), &manifest)?;
+
     Ok(())
 }
+
+fn is_unsafe_char(c: char) -> bool {
+    matches!(c, '/' | '\\' | ':') || c.is_control()
+}
+
+/// Check that a name can't point anywhere but a file directly inside the output directory
+///
+/// Names Windows would treat as devices or silently change by dropping a trailing dot or space
+/// are unsafe as well.
+fn is_safe_filename(name: &str) -> bool {
+    !matches!(name, "" | "." | "..")
+        && !name.chars().any(is_unsafe_char)
+        && !name.ends_with(['.', ' '])
+        && !is_reserved_filename(name)
+}
+
+/// Check for the device names Windows reserves, with or without an extension
+fn is_reserved_filename(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    let stem = stem.to_ascii_uppercase();
+
+    match stem.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => match stem
+            .strip_prefix("COM")
+            .or_else(|| stem.strip_prefix("LPT"))
+        {
+            Some(number) => number.len() == 1 && number.chars().all(|c| c.is_ascii_digit()),
+            None => false,
+        },
+    }
+}
+
+fn sanitize_filename(name: &str) -> String {
+    let mut sanitized = name
+        .chars()
+        .map(|c| if is_unsafe_char(c) { '_' } else { c })
+        .collect::<String>();
+
+    if matches!(sanitized.as_str(), "" | "." | "..") {
+        return "_".repeat(sanitized.len().max(1));
+    }
+
+    let trimmed_len = sanitized.trim_end_matches(['.', ' ']).len();
+    let trailing_len = sanitized.len() - trimmed_len;
+    sanitized.replace_range(trimmed_len.., &"_".repeat(trailing_len));
+
+    if is_reserved_filename(&sanitized) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// Add a numbered suffix to the name if it's already used, ignoring case
+fn unique_filename(name: &str, used_names: &mut HashSet<String>) -> String {
+    let mut candidate = name.to_string();
+    let mut suffix = 1;
+    while !used_names.insert(candidate.to_ascii_uppercase()) {
+        candidate = match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => {
+                format!("{}_{}.{}", stem, suffix, extension)
+            }
+            _ => format!("{}_{}", name, suffix),
+        };
+        suffix += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{is_safe_filename, sanitize_filename, unique_filename};
+
+    #[test]
+    fn test_safe_filename() {
+        for name in [
+            "model.ape",
+            "tex_01.tga",
+            "..name",
+            "con_model.ape",
+            "com10.txt",
+        ] {
+            assert!(is_safe_filename(name), "{:?} is safe", name);
+        }
+
+        for name in [
+            "",
+            ".",
+            "..",
+            "../model.ape",
+            "..\\model.ape",
+            "/etc/passwd",
+            "\\server\\share",
+            "C:model.ape",
+            "C:\\model.ape",
+            "model\0.ape",
+            "CON",
+            "nul.txt",
+            "Aux.tar.gz",
+            "com1",
+            "LPT9.ape",
+            "prn .ape",
+            "model.",
+            "model ",
+        ] {
+            assert!(!is_safe_filename(name), "{:?} is unsafe", name);
+        }
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        for (name, expected) in [
+            ("model.ape", "model.ape"),
+            ("", "_"),
+            (".", "_"),
+            ("..", "__"),
+            ("../model.ape", ".._model.ape"),
+            ("/etc/passwd", "_etc_passwd"),
+            ("C:\\model.ape", "C__model.ape"),
+            ("CON", "_CON"),
+            ("nul.txt", "_nul.txt"),
+            ("model.", "model_"),
+            ("model. ", "model__"),
+            ("com1 .ape", "_com1 .ape"),
+        ] {
+            let sanitized = sanitize_filename(name);
+            assert_eq!(sanitized, expected, "sanitized {:?}", name);
+            assert!(is_safe_filename(&sanitized), "{:?} is safe", sanitized);
+        }
+    }
+
+    #[test]
+    fn test_unique_filename() {
+        let mut used_names = HashSet::new();
+        let names = [
+            "model.ape",
+            "MODEL.APE",
+            "model.ape",
+            "readme",
+            "README",
+            ".hidden",
+        ]
+        .iter()
+        .map(|name| unique_filename(name, &mut used_names))
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            [
+                "model.ape",
+                "MODEL_1.APE",
+                "model_2.ape",
+                "readme",
+                "README_1",
+                ".hidden"
+            ],
+            "unique names"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use clap::Parser;
+use fang::{
+    mst::{
+        crc::CrcWriter,
+        entry::{CanonicalEntry, Entry},
+        Mst, MstVersionKnown,
+    },
+    BinReaderExt,
+};
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+};
+
+#[derive(Parser, Debug)]
+pub struct VerifyOpts {
+    /// Path to MST
+    #[clap(short = 'i', long)]
+    input_path: String,
+    /// Check the entries' checksums as CRC-32, the engine's checksum algorithm is not confirmed yet
+    #[clap(long)]
+    crcs: bool,
+}
+
+pub fn verify_mst(opts: VerifyOpts) -> anyhow::Result<()> {
+    let mut file = BufReader::new(File::open(&opts.input_path)?);
+    let file_size = file.get_ref().metadata()?.len();
+
+    let mst = file.read_le::<Mst>()?;
+    let version = MstVersionKnown::try_from(mst.body.version())?;
+    let bytes_in_file = mst.body.header.bytes_in_file as u64;
+    let entries = mst.collect_entries();
+
+    let mut problems = 0;
+
+    if bytes_in_file > file_size {
+        println!(
+            "Header claims {} bytes, but the file is only {} bytes long",
+            bytes_in_file, file_size
+        );
+        problems += 1;
+    }
+
+    // Check that every entry is within the archive
+    for entry in &entries {
+        let end = entry.offset() as u64 + entry.size() as u64;
+        if end > bytes_in_file {
+            println!(
+                "{: <20}  ends at {} which is past the end of the archive ({})",
+                entry.filename(),
+                end,
+                bytes_in_file
+            );
+            problems += 1;
+        }
+    }
+
+    // Check that no two entries share any data
+    for (a, b) in find_overlaps(&entries) {
+        println!(
+            "{: <20}  overlaps {} ({}..{} and {}..{})",
+            a.filename(),
+            b.filename(),
+            a.offset(),
+            a.offset() + a.size(),
+            b.offset(),
+            b.offset() + b.size()
+        );
+        problems += 1;
+    }
+
+    // Check the checksum of every entry whose data is present, V160 archives don't have checksums
+    if opts.crcs && version != MstVersionKnown::V160 {
+        for entry in &entries {
+            if entry.offset() as u64 + entry.size() as u64 > file_size {
+                continue;
+            }
+
+            file.seek(SeekFrom::Start(entry.offset() as u64))?;
+            let mut crc_writer = CrcWriter::new(std::io::sink());
+            std::io::copy(&mut (&mut file).take(entry.size() as u64), &mut crc_writer)?;
+
+            let crc = crc_writer.crc();
+            if crc != entry.crc {
+                println!(
+                    "{: <20}  checksum {:08x} does not match its data ({:08x})",
+                    entry.filename(),
+                    entry.crc,
+                    crc
+                );
+                problems += 1;
+            }
+        }
+    }
+
+    if problems > 0 {
+        anyhow::bail!("found {} problems in {} entries", problems, entries.len());
+    }
+
+    println!("No problems found in {} entries", entries.len());
+
+    Ok(())
+}
+
+/// Pairs of entries whose data overlaps, ordered by offset
+///
+/// Each entry is compared with the one reaching the furthest so far, so an entry that contains several
+/// later ones overlaps all of them.
+fn find_overlaps(entries: &[CanonicalEntry]) -> Vec<(&CanonicalEntry, &CanonicalEntry)> {
+    let mut sorted_entries = entries.iter().filter(|e| e.size() > 0).collect::<Vec<_>>();
+    sorted_entries.sort_by_key(|e| e.offset());
+
+    let mut overlaps = Vec::new();
+    let mut furthest: Option<&CanonicalEntry> = None;
+    for entry in sorted_entries {
+        match furthest {
+            Some(previous) => {
+                let previous_end = previous.offset() + previous.size();
+                if previous_end > entry.offset() {
+                    overlaps.push((previous, entry));
+                }
+                if entry.offset() + entry.size() > previous_end {
+                    furthest = Some(entry);
+                }
+            }
+            None => furthest = Some(entry),
+        }
+    }
+    overlaps
+}
+
+#[cfg(test)]
+mod tests {
+    use fang::mst::entry::{CanonicalEntry, Entry};
+
+    use super::find_overlaps;
+
+    #[test]
+    fn test_find_overlaps() {
+        let entries = [("a", 0, 100), ("b", 10, 10), ("c", 30, 10), ("d", 100, 5)]
+            .iter()
+            .map(|(name, offset, size)| CanonicalEntry {
+                filename: (&name.to_string()).into(),
+                offset: *offset,
+                size: *size,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        let overlaps = find_overlaps(&entries)
+            .iter()
+            .map(|(a, b)| (a.filename(), b.filename()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            overlaps,
+            [
+                ("a".to_string(), "b".to_string()),
+                ("a".to_string(), "c".to_string())
+            ],
+            "entries inside an earlier entry"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use clap::Parser;
-use fang::{rdg::snd_init::SndInitRdg, BinReaderExt};
+use fang::rdg::snd_init::SndInitRdg;
 use std::{fs::File, io::BufReader, path::Path};
 
 #[derive(Parser, Debug)]
@@ -14,7 +14,7 @@ pub fn info_rdg(opts: InfoOpts) -> anyhow::Result<()> {
 
     let snd_init_path = input_dir.join("snd_init.rdg");
     let mut snd_init_file = BufReader::new(File::open(&snd_init_path)?);
-    let snd_init_rdg = snd_init_file.read_be::<SndInitRdg>()?;
+    let snd_init_rdg = SndInitRdg::read_from(&mut snd_init_file)?;
     println!("{:#?}", &snd_init_rdg);
 
     Ok(())
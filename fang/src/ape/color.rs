@@ -1,13 +1,13 @@
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
 
-#[derive(BinRead, Debug)]
+#[derive(BinRead, BinWrite, Debug)]
 pub struct CFColorRGB {
     pub red: f32,
     pub green: f32,
     pub blue: f32,
 }
 
-#[derive(BinRead, Debug)]
+#[derive(BinRead, BinWrite, Debug)]
 pub struct CFColorRGBA {
     pub red: f32,
     pub green: f32,
@@ -15,7 +15,7 @@ pub struct CFColorRGBA {
     pub alpha: f32,
 }
 
-#[derive(BinRead, Debug)]
+#[derive(BinRead, BinWrite, Debug)]
 pub struct CFColorMotif {
     pub red: f32,
     pub green: f32,
@@ -0,0 +1,163 @@
+use binrw::{BinRead, BinWrite};
+use modular_bitfield::prelude::*;
+
+/// Kind of light, as stored in FLightInit
+///
+/// Values follow the order of the engine's light types, anything past them is kept as it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightKind {
+    Directional,
+    Omni,
+    Spot,
+    Ambient,
+    Unknown(u8),
+}
+
+impl LightKind {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => LightKind::Directional,
+            1 => LightKind::Omni,
+            2 => LightKind::Spot,
+            3 => LightKind::Ambient,
+            value => LightKind::Unknown(value),
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            LightKind::Directional => 0,
+            LightKind::Omni => 1,
+            LightKind::Spot => 2,
+            LightKind::Ambient => 3,
+            LightKind::Unknown(value) => value,
+        }
+    }
+}
+
+/// Declare a bitfield over a raw integer with a named flag per bit, lowest bit first
+///
+/// Bits are read as a whole integer in the byte order of the ape, so bit 0 is always the lowest bit
+/// of the value. The debug output lists the names of the bits that are set.
+macro_rules! flags {
+    ($(#[$meta:meta])* $name:ident($int:ty) { $($bit:ident),* $(,)? }) => {
+        $(#[$meta])*
+        #[bitfield]
+        #[derive(BinRead, BinWrite, Clone, Copy, PartialEq, Eq, Default)]
+        #[br(map = |x: $int| Self::from_bytes(x.to_le_bytes()))]
+        #[bw(map = |x: &$name| <$int>::from_le_bytes(Self::into_bytes(*x)))]
+        pub struct $name {
+            $(pub $bit: bool,)*
+        }
+
+        impl $name {
+            /// Names of the bits, lowest first
+            pub const BIT_NAMES: &'static [&'static str] = &[$(stringify!($bit)),*];
+
+            pub fn from_raw(raw: $int) -> Self {
+                Self::from_bytes(raw.to_le_bytes())
+            }
+
+            pub fn raw(&self) -> $int {
+                <$int>::from_le_bytes(self.into_bytes())
+            }
+
+            /// Names of the bits that are set, lowest first
+            pub fn set_flags(&self) -> Vec<&'static str> {
+                set_flags(u32::from(self.raw()), Self::BIT_NAMES)
+            }
+        }
+
+        impl std::fmt::Debug for $name {
+            fn fmt(
+                &self,
+                fmt: &mut std::fmt::Formatter<'_>,
+            ) -> std::result::Result<(), std::fmt::Error> {
+                let hex_digits = std::mem::size_of::<$int>() * 2;
+                let raw = u32::from(self.raw());
+                fmt_flags(fmt, stringify!($name), raw, hex_digits, &self.set_flags())
+            }
+        }
+    };
+}
+
+// None of the bits have been matched to what they do in the engine yet. They're named after their
+// position until they are, renaming one here is all it takes.
+
+flags! {
+    /// Flags of an Ape
+    ApeFlags(u16) {
+        unknown_0, unknown_1, unknown_2, unknown_3,
+        unknown_4, unknown_5, unknown_6, unknown_7,
+        unknown_8, unknown_9, unknown_10, unknown_11,
+        unknown_12, unknown_13, unknown_14, unknown_15,
+    }
+}
+
+flags! {
+    /// Flags of an FMeshBone
+    BoneFlags(u8) {
+        unknown_0, unknown_1, unknown_2, unknown_3,
+        unknown_4, unknown_5, unknown_6, unknown_7,
+    }
+}
+
+flags! {
+    /// Flags of an FMeshMaterial
+    MaterialFlags(u16) {
+        unknown_0, unknown_1, unknown_2, unknown_3,
+        unknown_4, unknown_5, unknown_6, unknown_7,
+        unknown_8, unknown_9, unknown_10, unknown_11,
+        unknown_12, unknown_13, unknown_14, unknown_15,
+    }
+}
+
+flags! {
+    /// Flags of an FLightInit
+    LightFlags(u32) {
+        unknown_0, unknown_1, unknown_2, unknown_3,
+        unknown_4, unknown_5, unknown_6, unknown_7,
+        unknown_8, unknown_9, unknown_10, unknown_11,
+        unknown_12, unknown_13, unknown_14, unknown_15,
+        unknown_16, unknown_17, unknown_18, unknown_19,
+        unknown_20, unknown_21, unknown_22, unknown_23,
+        unknown_24, unknown_25, unknown_26, unknown_27,
+        unknown_28, unknown_29, unknown_30, unknown_31,
+    }
+}
+
+fn set_flags(value: u32, names: &[&'static str]) -> Vec<&'static str> {
+    names
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| value & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Print the raw value and the names of the bits that are set
+///
+/// Looks like `ApeFlags(0x0005, unknown_0 | unknown_2)`, or `ApeFlags(0x0000)` without any.
+fn fmt_flags(
+    fmt: &mut std::fmt::Formatter<'_>,
+    name: &str,
+    value: u32,
+    hex_digits: usize,
+    set_flags: &[&str],
+) -> std::result::Result<(), std::fmt::Error> {
+    match set_flags.is_empty() {
+        true => fmt.write_fmt(format_args!(
+            "{}({:#0width$x})",
+            name,
+            value,
+            width = hex_digits + 2
+        )),
+        false => fmt.write_fmt(format_args!(
+            "{}({:#0width$x}, {})",
+            name,
+            value,
+            set_flags.join(" | "),
+            width = hex_digits + 2
+        )),
+    }
+}
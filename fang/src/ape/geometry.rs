@@ -1,6 +1,13 @@
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
+use std::ops::{Add, Mul, Neg, Sub};
 
-#[derive(BinRead)]
+#[cfg(feature = "glam")]
+mod glam_convert;
+
+#[cfg(feature = "mint")]
+mod mint_convert;
+
+#[derive(BinRead, BinWrite, Clone, Copy, PartialEq, Default)]
 pub struct CFVec2 {
     pub x: f32,
     pub y: f32,
@@ -12,7 +19,7 @@ impl std::fmt::Debug for CFVec2 {
     }
 }
 
-#[derive(BinRead)]
+#[derive(BinRead, BinWrite, Clone, Copy, PartialEq, Default)]
 pub struct CFVec3 {
     pub x: f32,
     pub y: f32,
@@ -25,7 +32,7 @@ impl std::fmt::Debug for CFVec3 {
     }
 }
 
-#[derive(BinRead)]
+#[derive(BinRead, BinWrite, Clone, Copy, PartialEq, Default)]
 pub struct CFMtx43 {
     pub x: CFVec3,
     pub y: CFVec3,
@@ -42,7 +49,7 @@ impl std::fmt::Debug for CFMtx43 {
     }
 }
 
-#[derive(BinRead)]
+#[derive(BinRead, BinWrite, Clone, Copy, PartialEq, Default)]
 pub struct CFVec3A {
     pub x: f32,
     pub y: f32,
@@ -59,7 +66,7 @@ impl std::fmt::Debug for CFVec3A {
     }
 }
 
-#[derive(BinRead)]
+#[derive(BinRead, BinWrite, Clone, Copy, PartialEq, Default)]
 pub struct CFMtx43A {
     pub x: CFVec3A,
     pub y: CFVec3A,
@@ -78,8 +85,303 @@ impl std::fmt::Debug for CFMtx43A {
     }
 }
 
-#[derive(BinRead, Debug)]
+/// Rotation as a unit quaternion
+#[derive(BinRead, BinWrite, Clone, Copy, PartialEq)]
+pub struct CFQuat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl std::fmt::Debug for CFQuat {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        fmt.write_fmt(format_args!(
+            "CFQuat({}, {}, {}, {})",
+            self.x, self.y, self.z, self.w
+        ))
+    }
+}
+
+impl Default for CFQuat {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[derive(BinRead, BinWrite, Debug, Clone, Copy, PartialEq, Default)]
 pub struct CFSphere {
     pub radius: f32,
     pub pos: CFVec3,
 }
+
+impl CFVec3 {
+    pub const ZERO: Self = Self::new(0.0, 0.0, 0.0);
+
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn dot(&self, other: &Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn distance(&self, other: &Self) -> f32 {
+        (*other - *self).length()
+    }
+}
+
+impl Add for CFVec3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for CFVec3 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<f32> for CFVec3 {
+    type Output = Self;
+
+    fn mul(self, scale: f32) -> Self {
+        Self::new(self.x * scale, self.y * scale, self.z * scale)
+    }
+}
+
+impl Neg for CFVec3 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl CFVec3A {
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    pub const fn from_vec3(v: CFVec3, w: f32) -> Self {
+        Self::new(v.x, v.y, v.z, w)
+    }
+
+    /// The vector without its fourth component
+    pub fn xyz(&self) -> CFVec3 {
+        CFVec3::new(self.x, self.y, self.z)
+    }
+}
+
+impl CFQuat {
+    pub const IDENTITY: Self = Self::new(0.0, 0.0, 0.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    pub fn length(&self) -> f32 {
+        (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        let length = self.length();
+        Self::new(
+            self.x / length,
+            self.y / length,
+            self.z / length,
+            self.w / length,
+        )
+    }
+
+    /// Rotate a vector, the quaternion has to be normalized
+    pub fn rotate(&self, v: &CFVec3) -> CFVec3 {
+        let q = CFVec3::new(self.x, self.y, self.z);
+        let t = q.cross(v) * 2.0;
+        *v + t * self.w + q.cross(&t)
+    }
+}
+
+/// Matrices are applied to row vectors like the engine does, `x`, `y` and `z` are where the axes end up and
+/// `p` is the translation. The fourth components are 0 for the axes and 1 for the translation.
+impl CFMtx43A {
+    pub const IDENTITY: Self = Self::from_rows(
+        CFVec3::new(1.0, 0.0, 0.0),
+        CFVec3::new(0.0, 1.0, 0.0),
+        CFVec3::new(0.0, 0.0, 1.0),
+        CFVec3::ZERO,
+    );
+
+    pub const fn from_rows(x: CFVec3, y: CFVec3, z: CFVec3, p: CFVec3) -> Self {
+        Self {
+            x: CFVec3A::from_vec3(x, 0.0),
+            y: CFVec3A::from_vec3(y, 0.0),
+            z: CFVec3A::from_vec3(z, 0.0),
+            p: CFVec3A::from_vec3(p, 1.0),
+        }
+    }
+
+    /// Scale, then rotate, then translate
+    pub fn from_scale_rotation_translation(
+        scale: CFVec3,
+        rotation: CFQuat,
+        translation: CFVec3,
+    ) -> Self {
+        Self::from_rows(
+            rotation.rotate(&CFVec3::new(1.0, 0.0, 0.0)) * scale.x,
+            rotation.rotate(&CFVec3::new(0.0, 1.0, 0.0)) * scale.y,
+            rotation.rotate(&CFVec3::new(0.0, 0.0, 1.0)) * scale.z,
+            translation,
+        )
+    }
+
+    /// Transform a direction, ignoring the translation
+    pub fn transform_vector(&self, v: &CFVec3) -> CFVec3 {
+        self.x.xyz() * v.x + self.y.xyz() * v.y + self.z.xyz() * v.z
+    }
+
+    pub fn transform_point(&self, point: &CFVec3) -> CFVec3 {
+        self.transform_vector(point) + self.p.xyz()
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.x.xyz().dot(&self.y.xyz().cross(&self.z.xyz()))
+    }
+
+    /// Inverse of the matrix, None if it collapses space and can't be inverted
+    pub fn inverse(&self) -> Option<Self> {
+        let determinant = self.determinant();
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+
+        let (x, y, z) = (self.x.xyz(), self.y.xyz(), self.z.xyz());
+        // Columns of the inverted 3x3 part
+        let columns = [
+            y.cross(&z) * (1.0 / determinant),
+            z.cross(&x) * (1.0 / determinant),
+            x.cross(&y) * (1.0 / determinant),
+        ];
+        let mut inverse = Self::from_rows(
+            CFVec3::new(columns[0].x, columns[1].x, columns[2].x),
+            CFVec3::new(columns[0].y, columns[1].y, columns[2].y),
+            CFVec3::new(columns[0].z, columns[1].z, columns[2].z),
+            CFVec3::ZERO,
+        );
+        inverse.p = CFVec3A::from_vec3(-inverse.transform_vector(&self.p.xyz()), 1.0);
+        Some(inverse)
+    }
+
+    /// Split the matrix into its translation, rotation and scale
+    ///
+    /// Mirroring shows up as a negative x scale. Matrices with a zero scale get the identity rotation.
+    pub fn decompose(&self) -> (CFVec3, CFQuat, CFVec3) {
+        let (x, y, z) = (self.x.xyz(), self.y.xyz(), self.z.xyz());
+        let mut scale = CFVec3::new(x.length(), y.length(), z.length());
+        if self.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+
+        let rotation = if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+            CFQuat::IDENTITY
+        } else {
+            quat_from_axes(
+                &(x * (1.0 / scale.x)),
+                &(y * (1.0 / scale.y)),
+                &(z * (1.0 / scale.z)),
+            )
+        };
+
+        (self.p.xyz(), rotation, scale)
+    }
+}
+
+/// Applies `self` first, then `other`
+impl Mul for CFMtx43A {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::from_rows(
+            other.transform_vector(&self.x.xyz()),
+            other.transform_vector(&self.y.xyz()),
+            other.transform_vector(&self.z.xyz()),
+            other.transform_point(&self.p.xyz()),
+        )
+    }
+}
+
+/// Rotation that moves the x, y and z axes to the given orthonormal axes
+fn quat_from_axes(x: &CFVec3, y: &CFVec3, z: &CFVec3) -> CFQuat {
+    let trace = x.x + y.y + z.z;
+    let quat = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        CFQuat::new((y.z - z.y) / s, (z.x - x.z) / s, (x.y - y.x) / s, 0.25 * s)
+    } else if x.x > y.y && x.x > z.z {
+        let s = (1.0 + x.x - y.y - z.z).sqrt() * 2.0;
+        CFQuat::new(0.25 * s, (y.x + x.y) / s, (z.x + x.z) / s, (y.z - z.y) / s)
+    } else if y.y > z.z {
+        let s = (1.0 + y.y - x.x - z.z).sqrt() * 2.0;
+        CFQuat::new((y.x + x.y) / s, 0.25 * s, (z.y + y.z) / s, (z.x - x.z) / s)
+    } else {
+        let s = (1.0 + z.z - x.x - y.y).sqrt() * 2.0;
+        CFQuat::new((z.x + x.z) / s, (z.y + y.z) / s, 0.25 * s, (x.y - y.x) / s)
+    };
+    quat.normalize()
+}
+
+impl CFSphere {
+    pub const fn new(pos: CFVec3, radius: f32) -> Self {
+        Self { radius, pos }
+    }
+
+    pub fn contains(&self, point: &CFVec3) -> bool {
+        self.pos.distance(point) <= self.radius
+    }
+
+    pub fn contains_sphere(&self, other: &CFSphere) -> bool {
+        self.pos.distance(&other.pos) + other.radius <= self.radius
+    }
+
+    /// Smallest sphere that contains both spheres
+    pub fn merge(&self, other: &CFSphere) -> CFSphere {
+        if self.contains_sphere(other) {
+            return *self;
+        }
+        if other.contains_sphere(self) {
+            return *other;
+        }
+
+        let distance = self.pos.distance(&other.pos);
+        let radius = (distance + self.radius + other.radius) * 0.5;
+        let pos = self.pos + (other.pos - self.pos) * ((radius - self.radius) / distance);
+        CFSphere::new(pos, radius)
+    }
+
+    /// Sphere that contains this one after transforming it, scaled by the largest scale of the matrix
+    pub fn transform(&self, mtx: &CFMtx43A) -> CFSphere {
+        let scale = mtx
+            .x
+            .xyz()
+            .length()
+            .max(mtx.y.xyz().length())
+            .max(mtx.z.xyz().length());
+        CFSphere::new(mtx.transform_point(&self.pos), self.radius * scale)
+    }
+}
@@ -0,0 +1,102 @@
+use glam::{Affine3A, Mat3, Quat, Vec2, Vec3, Vec3A, Vec4};
+
+use super::{CFMtx43, CFMtx43A, CFQuat, CFVec2, CFVec3, CFVec3A};
+
+impl From<CFVec2> for Vec2 {
+    fn from(v: CFVec2) -> Self {
+        Vec2::new(v.x, v.y)
+    }
+}
+
+impl From<Vec2> for CFVec2 {
+    fn from(v: Vec2) -> Self {
+        CFVec2 { x: v.x, y: v.y }
+    }
+}
+
+impl From<CFVec3> for Vec3 {
+    fn from(v: CFVec3) -> Self {
+        Vec3::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<Vec3> for CFVec3 {
+    fn from(v: Vec3) -> Self {
+        CFVec3::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<CFVec3> for Vec3A {
+    fn from(v: CFVec3) -> Self {
+        Vec3A::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<Vec3A> for CFVec3 {
+    fn from(v: Vec3A) -> Self {
+        CFVec3::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<CFVec3A> for Vec4 {
+    fn from(v: CFVec3A) -> Self {
+        Vec4::new(v.x, v.y, v.z, v.w)
+    }
+}
+
+impl From<Vec4> for CFVec3A {
+    fn from(v: Vec4) -> Self {
+        CFVec3A::new(v.x, v.y, v.z, v.w)
+    }
+}
+
+impl From<CFQuat> for Quat {
+    fn from(q: CFQuat) -> Self {
+        Quat::from_xyzw(q.x, q.y, q.z, q.w)
+    }
+}
+
+impl From<Quat> for CFQuat {
+    fn from(q: Quat) -> Self {
+        CFQuat::new(q.x, q.y, q.z, q.w)
+    }
+}
+
+/// The rows of a Fang matrix are the columns of a glam one
+impl From<CFMtx43> for Mat3 {
+    fn from(m: CFMtx43) -> Self {
+        Mat3::from_cols(m.x.into(), m.y.into(), m.z.into())
+    }
+}
+
+impl From<Mat3> for CFMtx43 {
+    fn from(m: Mat3) -> Self {
+        CFMtx43 {
+            x: m.x_axis.into(),
+            y: m.y_axis.into(),
+            z: m.z_axis.into(),
+        }
+    }
+}
+
+impl From<CFMtx43A> for Affine3A {
+    fn from(m: CFMtx43A) -> Self {
+        Affine3A::from_cols(
+            m.x.xyz().into(),
+            m.y.xyz().into(),
+            m.z.xyz().into(),
+            m.p.xyz().into(),
+        )
+    }
+}
+
+impl From<Affine3A> for CFMtx43A {
+    fn from(m: Affine3A) -> Self {
+        CFMtx43A::from_rows(
+            m.matrix3.x_axis.into(),
+            m.matrix3.y_axis.into(),
+            m.matrix3.z_axis.into(),
+            m.translation.into(),
+        )
+    }
+}
@@ -0,0 +1,105 @@
+use mint::{ColumnMatrix3, ColumnMatrix3x4, Quaternion, Vector2, Vector3, Vector4};
+
+use super::{CFMtx43, CFMtx43A, CFQuat, CFVec2, CFVec3, CFVec3A};
+
+impl From<CFVec2> for Vector2<f32> {
+    fn from(v: CFVec2) -> Self {
+        Vector2 { x: v.x, y: v.y }
+    }
+}
+
+impl From<Vector2<f32>> for CFVec2 {
+    fn from(v: Vector2<f32>) -> Self {
+        CFVec2 { x: v.x, y: v.y }
+    }
+}
+
+impl From<CFVec3> for Vector3<f32> {
+    fn from(v: CFVec3) -> Self {
+        Vector3 {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+impl From<Vector3<f32>> for CFVec3 {
+    fn from(v: Vector3<f32>) -> Self {
+        CFVec3::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<CFVec3A> for Vector4<f32> {
+    fn from(v: CFVec3A) -> Self {
+        Vector4 {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+            w: v.w,
+        }
+    }
+}
+
+impl From<Vector4<f32>> for CFVec3A {
+    fn from(v: Vector4<f32>) -> Self {
+        CFVec3A::new(v.x, v.y, v.z, v.w)
+    }
+}
+
+impl From<CFQuat> for Quaternion<f32> {
+    fn from(q: CFQuat) -> Self {
+        Quaternion {
+            v: Vector3 {
+                x: q.x,
+                y: q.y,
+                z: q.z,
+            },
+            s: q.w,
+        }
+    }
+}
+
+impl From<Quaternion<f32>> for CFQuat {
+    fn from(q: Quaternion<f32>) -> Self {
+        CFQuat::new(q.v.x, q.v.y, q.v.z, q.s)
+    }
+}
+
+/// The rows of a Fang matrix are the columns of a mint one
+impl From<CFMtx43> for ColumnMatrix3<f32> {
+    fn from(m: CFMtx43) -> Self {
+        ColumnMatrix3 {
+            x: m.x.into(),
+            y: m.y.into(),
+            z: m.z.into(),
+        }
+    }
+}
+
+impl From<ColumnMatrix3<f32>> for CFMtx43 {
+    fn from(m: ColumnMatrix3<f32>) -> Self {
+        CFMtx43 {
+            x: m.x.into(),
+            y: m.y.into(),
+            z: m.z.into(),
+        }
+    }
+}
+
+impl From<CFMtx43A> for ColumnMatrix3x4<f32> {
+    fn from(m: CFMtx43A) -> Self {
+        ColumnMatrix3x4 {
+            x: m.x.xyz().into(),
+            y: m.y.xyz().into(),
+            z: m.z.xyz().into(),
+            w: m.p.xyz().into(),
+        }
+    }
+}
+
+impl From<ColumnMatrix3x4<f32>> for CFMtx43A {
+    fn from(m: ColumnMatrix3x4<f32>) -> Self {
+        CFMtx43A::from_rows(m.x.into(), m.y.into(), m.z.into(), m.w.into())
+    }
+}
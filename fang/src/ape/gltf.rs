@@ -0,0 +1,249 @@
+use serde::{Serialize, Serializer};
+
+use super::{geometry::CFMtx43A, Ape};
+
+/// Minimal glTF 2.0 document with the skeleton and materials of an ape, but no geometry
+///
+/// Serialize it as JSON to get a `.gltf` file. The platform specific mesh data isn't parsed yet,
+/// so the document only has the skeleton as a node hierarchy and skin, and the materials. Binary
+/// data is embedded in the buffers as base64 data URIs.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfDocument {
+    pub asset: GltfAsset,
+    pub scene: usize,
+    pub scenes: Vec<GltfScene>,
+    pub nodes: Vec<GltfNode>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skins: Vec<GltfSkin>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<GltfMaterial>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub accessors: Vec<GltfAccessor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub buffer_views: Vec<GltfBufferView>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub buffers: Vec<GltfBuffer>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GltfAsset {
+    pub version: String,
+    pub generator: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GltfScene {
+    pub name: String,
+    pub nodes: Vec<usize>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GltfNode {
+    pub name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<usize>,
+    /// Column major 4x4 transform relative to the parent node
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<[f32; 16]>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfSkin {
+    pub name: String,
+    /// Nodes of the bones, in bone order
+    pub joints: Vec<usize>,
+    /// Accessor of a matrix per joint, from model space to the joint's space
+    pub inverse_bind_matrices: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfAccessor {
+    pub buffer_view: usize,
+    pub component_type: u32,
+    pub count: usize,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfBufferView {
+    pub buffer: usize,
+    pub byte_length: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfBuffer {
+    pub byte_length: usize,
+    /// Little endian contents of the buffer, written as a data URI
+    #[serde(rename = "uri", serialize_with = "serialize_data_uri")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfMaterial {
+    pub name: String,
+    pub pbr_metallic_roughness: GltfPbrMetallicRoughness,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GltfPbrMetallicRoughness {
+    pub base_color_factor: [f32; 4],
+    pub metallic_factor: f32,
+}
+
+/// Accessor component type of 32 bit floats
+const COMPONENT_TYPE_FLOAT: u32 = 5126;
+
+impl GltfDocument {
+    /// Export the skeleton and materials of an ape
+    ///
+    /// The model gets a root node named after it, with a node per bone below it. Bones are linked
+    /// through their parent index and placed with their at rest bone to parent matrix. The bones also
+    /// make up a skin, with their at rest model to bone matrices as inverse bind matrices. Fang is left
+    /// handed, so everything is mirrored along Z to end up in glTF's right handed space.
+    pub fn from_ape(ape: &Ape) -> Self {
+        let bone_count = ape.bones.len();
+
+        // Node 0 is the model itself, bone i is node i + 1
+        let mut nodes = Vec::with_capacity(bone_count + 1);
+        nodes.push(GltfNode {
+            name: ape.name.to_string(),
+            children: Vec::new(),
+            matrix: None,
+        });
+        nodes.extend(ape.bones.iter().map(|bone| GltfNode {
+            name: bone.name.to_string(),
+            children: Vec::new(),
+            matrix: Some(to_gltf_matrix(&bone.at_rest_bone_to_parent_mtx)),
+        }));
+
+        for (index, bone) in ape.bones.iter().enumerate() {
+            let parent_index = bone.skeleton.parent_bone_index as usize;
+            // Root bones have no valid parent, and a bone can't be its own parent
+            let parent_node = match parent_index < bone_count && parent_index != index {
+                true => parent_index + 1,
+                false => 0,
+            };
+            nodes[parent_node].children.push(index + 1);
+        }
+
+        let materials = ape
+            .materials
+            .iter()
+            .enumerate()
+            .map(|(index, material)| GltfMaterial {
+                name: format!("{}_material_{}", ape.name, index),
+                pbr_metallic_roughness: GltfPbrMetallicRoughness {
+                    base_color_factor: [
+                        material.material_tint.red,
+                        material.material_tint.green,
+                        material.material_tint.blue,
+                        1.0,
+                    ],
+                    metallic_factor: 0.0,
+                },
+            })
+            .collect();
+
+        // The skin needs at least one joint
+        let mut skins = Vec::new();
+        let mut accessors = Vec::new();
+        let mut buffer_views = Vec::new();
+        let mut buffers = Vec::new();
+        if bone_count > 0 {
+            let data = ape
+                .bones
+                .iter()
+                .flat_map(|bone| to_gltf_matrix(&bone.at_rest_model_to_bone_mtx))
+                .flat_map(f32::to_le_bytes)
+                .collect::<Vec<_>>();
+
+            skins.push(GltfSkin {
+                name: ape.name.to_string(),
+                joints: (1..=bone_count).collect(),
+                inverse_bind_matrices: accessors.len(),
+            });
+            accessors.push(GltfAccessor {
+                buffer_view: buffer_views.len(),
+                component_type: COMPONENT_TYPE_FLOAT,
+                count: bone_count,
+                kind: "MAT4".to_string(),
+            });
+            buffer_views.push(GltfBufferView {
+                buffer: buffers.len(),
+                byte_length: data.len(),
+            });
+            buffers.push(GltfBuffer {
+                byte_length: data.len(),
+                data,
+            });
+        }
+
+        Self {
+            asset: GltfAsset {
+                version: "2.0".to_string(),
+                generator: format!("fang {}", env!("CARGO_PKG_VERSION")),
+            },
+            scene: 0,
+            scenes: vec![GltfScene {
+                name: ape.name.to_string(),
+                nodes: vec![0],
+            }],
+            nodes,
+            skins,
+            materials,
+            accessors,
+            buffer_views,
+            buffers,
+        }
+    }
+}
+
+/// Convert a Fang matrix to a column major glTF one, mirroring it along Z
+fn to_gltf_matrix(mtx: &CFMtx43A) -> [f32; 16] {
+    [
+        mtx.x.x, mtx.x.y, -mtx.x.z, 0.0, //
+        mtx.y.x, mtx.y.y, -mtx.y.z, 0.0, //
+        -mtx.z.x, -mtx.z.y, mtx.z.z, 0.0, //
+        mtx.p.x, mtx.p.y, -mtx.p.z, 1.0,
+    ]
+}
+
+fn serialize_data_uri<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!(
+        "data:application/octet-stream;base64,{}",
+        base64_encode(data)
+    ))
+}
+
+/// Standard base64 with padding
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let value = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for position in 0..4 {
+            match position <= chunk.len() {
+                true => {
+                    let index = (value >> (18 - 6 * position)) & 0x3f;
+                    encoded.push(ALPHABET[index as usize] as char);
+                }
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
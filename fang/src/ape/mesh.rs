@@ -1,41 +1,44 @@
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
 
 use super::{
     color::CFColorRGB,
+    flags::{BoneFlags, MaterialFlags},
     geometry::{CFMtx43A, CFSphere, CFVec3},
+    name::ApeName,
 };
 
-#[derive(BinRead, Debug)]
+#[derive(BinRead, BinWrite, Debug)]
 pub struct FMeshSegment {
     pub bound_sphere: CFSphere,
     pub bone_mtx_count: u8,
-    #[br(pad_after(3))]
     pub bone_mtx_indices: [u8; 4],
+    /// Unused, kept so writing the segment back doesn't change it
+    pub padding: [u8; 3],
 }
 
-#[derive(BinRead, Debug)]
+#[derive(BinRead, BinWrite, Debug)]
 pub struct FMeshSkeleton {
     pub parent_bone_index: u8,
     pub child_bone_count: u8,
     pub child_array_start_index: u8,
 }
 
-#[derive(BinRead, Debug)]
+#[derive(BinRead, BinWrite, Debug)]
 pub struct FMeshBone {
-    #[br(count = 32, map = crate::util::vec_to_null_terminated_str)]
-    pub name: String,
+    pub name: ApeName<32>,
     pub at_rest_bone_to_model_mtx: CFMtx43A,
     pub at_rest_model_to_bone_mtx: CFMtx43A,
     pub at_rest_parent_to_bone_mtx: CFMtx43A,
     pub at_rest_bone_to_parent_mtx: CFMtx43A,
     pub segmented_bound_sphere: CFSphere,
     pub skeleton: FMeshSkeleton,
-    pub flags: u8,
-    #[br(pad_after(11))]
+    pub flags: BoneFlags,
     pub part_id: u8,
+    /// Unused, kept so writing the bone back doesn't change it
+    pub padding: [u8; 11],
 }
 
-#[derive(BinRead, Debug)]
+#[derive(BinRead, BinWrite, Debug)]
 pub struct FMeshMaterial {
     pub off_sh_light_registers: u32,
     pub off_sh_surface_registers: u32,
@@ -57,10 +60,11 @@ pub struct FMeshMaterial {
     pub comp_affect_normals: [i8; 3],
     pub affect_bone_id: i8,
 
-    #[br(pad_after(1))]
     pub compressed_radius: u8,
+    /// Unused, kept so writing the material back doesn't change it
+    pub padding: u8,
 
-    pub mtl_flags: u16,
+    pub mtl_flags: MaterialFlags,
 
     pub draw_key: u32,
 
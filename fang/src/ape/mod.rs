@@ -1,24 +1,41 @@
-use binrw::{count, BinRead, FilePtr};
+use crate::error::TruncationReader;
+use binrw::{count, BinRead, BinReaderExt, BinWrite, BinWriterExt, FilePtr};
+use std::io::{Read, Seek, SeekFrom, Write};
 
 pub mod color;
 use self::color::*;
 
+pub mod flags;
+use self::flags::*;
+
 pub mod geometry;
 use self::geometry::*;
 
+pub mod gltf;
+
 pub mod mesh;
 use self::mesh::*;
 
+pub mod name;
+use self::name::*;
+
+pub mod patch;
+
+pub mod skeleton;
+
+/// A model with its skeleton, lights and materials
+///
+/// Only the header and the sections it points to are read, so an ape can't be written out on its own.
+/// [`Ape::write_in_place`] writes it back over the file it was read from.
 #[derive(BinRead, Debug)]
 pub struct Ape {
-    #[br(count = 16, map = crate::util::vec_to_null_terminated_str)]
-    pub name: String,
+    pub name: ApeName<16>,
 
     pub bound_sphere: CFSphere,
     pub bound_box_min: CFVec3,
     pub bound_box_max: CFVec3,
 
-    pub flags: u16,
+    pub flags: ApeFlags,
     pub mesh_coll_mask: u16,
 
     pub used_bone_count: u8,
@@ -45,30 +62,279 @@ pub struct Ape {
     #[br(parse_with = FilePtr::with(count(light_count as usize)))]
     pub lights: FilePtr<u32, Vec<FLightInit>>,
 
-    pub skeleton_indices: u32,
+    /// Child bone indices, each bone's children are a range of this starting at its child_array_start_index
+    #[br(parse_with = FilePtr::with(count(
+        bones.iter().map(|bone| bone.skeleton.child_bone_count as usize).sum::<usize>()
+    )))]
+    pub skeleton_indices: FilePtr<u32, Vec<u8>>,
 
     #[br(parse_with = FilePtr::with(count(material_count as usize)))]
     pub materials: FilePtr<u32, Vec<FMeshMaterial>>,
 
+    // The layouts behind these offsets aren't known yet, so they're kept as raw offsets
     pub collision_tree: u32,
     pub tex_layer_ids: u32,
     pub platform_specific_mesh: u32,
 }
 
-#[derive(BinRead, Debug)]
+/// Size of the fixed part of the ape header, up to and including the pointers
+const HEADER_SIZE: usize = 136;
+
+impl Ape {
+    /// Read an ape stored in the given byte order, GameCube apes are big endian
+    pub fn read_endian<R: Read + Seek>(reader: &mut R, is_little: bool) -> crate::Result<Self> {
+        let mut reader = TruncationReader::new(reader)?;
+        let ape = match is_little {
+            true => reader.read_le()?,
+            false => reader.read_be()?,
+        };
+        Ok(ape)
+    }
+
+    /// Read an ape in whichever byte order makes its header look the most plausible
+    ///
+    /// Falls back to the other byte order if the ape can't be read in the likelier one.
+    pub fn read_auto<R: Read + Seek>(reader: &mut R) -> crate::Result<Self> {
+        let start = reader.stream_position()?;
+        let is_little = Self::detect_little_endian(reader)?;
+
+        reader.seek(SeekFrom::Start(start))?;
+        match Self::read_endian(reader, is_little) {
+            Ok(ape) => Ok(ape),
+            Err(e) => {
+                reader.seek(SeekFrom::Start(start))?;
+                Self::read_endian(reader, !is_little).map_err(|_| e)
+            }
+        }
+    }
+
+    /// Write the ape over the file it was read from, in the given byte order
+    ///
+    /// Every section is written back at the offset it was read from, so the number of segments, bones,
+    /// lights and materials can't change. Sections whose layout isn't known, like the platform specific
+    /// mesh, are left as they are in the file.
+    pub fn write_in_place<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        is_little: bool,
+    ) -> crate::Result<()> {
+        check_section_len("segments", self.segments.len(), self.segment_count as usize)?;
+        check_section_len("bones", self.bones.len(), self.bone_count as usize)?;
+        check_section_len("lights", self.lights.len(), self.light_count as usize)?;
+        check_section_len(
+            "materials",
+            self.materials.len(),
+            self.material_count as usize,
+        )?;
+        let child_count = self
+            .bones
+            .iter()
+            .map(|bone| bone.skeleton.child_bone_count as usize)
+            .sum();
+        check_section_len("skeleton indices", self.skeleton_indices.len(), child_count)?;
+
+        writer.seek(SeekFrom::Start(0))?;
+        self.write_header(writer, is_little)?;
+
+        write_section(writer, self.segments.ptr, &*self.segments, is_little)?;
+        write_section(writer, self.bones.ptr, &*self.bones, is_little)?;
+        write_section(writer, self.lights.ptr, &*self.lights, is_little)?;
+        write_section(
+            writer,
+            self.skeleton_indices.ptr,
+            &*self.skeleton_indices,
+            is_little,
+        )?;
+        write_section(writer, self.materials.ptr, &*self.materials, is_little)?;
+
+        Ok(())
+    }
+
+    /// Write the fixed part of the header, pointing to the sections where they were read from
+    fn write_header<W: Write + Seek>(&self, writer: &mut W, is_little: bool) -> crate::Result<()> {
+        write_endian(writer, &self.name, is_little)?;
+        write_endian(writer, &self.bound_sphere, is_little)?;
+        write_endian(writer, &self.bound_box_min, is_little)?;
+        write_endian(writer, &self.bound_box_max, is_little)?;
+        write_endian(writer, &self.flags, is_little)?;
+        write_endian(writer, &self.mesh_coll_mask, is_little)?;
+
+        let counts = [
+            self.used_bone_count,
+            self.root_bone_index,
+            self.bone_count,
+            self.segment_count,
+            self.tex_layer_id_count,
+            self.tex_layer_id_count_st,
+            self.tex_layer_id_count_flip,
+            self.light_count,
+            self.material_count,
+            self.coll_tree_count,
+            self.lod_count,
+            self.shadow_lod_bias,
+        ];
+        write_endian(writer, &counts, is_little)?;
+        write_endian(writer, &self.load_distances, is_little)?;
+
+        let offsets = [
+            self.segments.ptr,
+            self.bones.ptr,
+            self.lights.ptr,
+            self.skeleton_indices.ptr,
+            self.materials.ptr,
+            self.collision_tree,
+            self.tex_layer_ids,
+            self.platform_specific_mesh,
+        ];
+        write_endian(writer, &offsets, is_little)?;
+
+        Ok(())
+    }
+
+    /// Indices of the children of a bone, according to the skeleton
+    pub fn bone_children(&self, bone_index: usize) -> Option<&[u8]> {
+        let skeleton = &self.bones.get(bone_index)?.skeleton;
+        let start = skeleton.child_array_start_index as usize;
+        let end = start + skeleton.child_bone_count as usize;
+        self.skeleton_indices.get(start..end)
+    }
+
+    /// Guess whether the ape at the reader's position is little endian, leaving the reader where it was
+    pub fn detect_little_endian<R: Read + Seek>(reader: &mut R) -> crate::Result<bool> {
+        let start = reader.stream_position()?;
+        let file_len = reader.seek(SeekFrom::End(0))? - start;
+
+        if file_len < HEADER_SIZE as u64 {
+            return Err(crate::Error::Truncated {
+                offset: start + file_len,
+                expected: HEADER_SIZE as u64 - file_len,
+            });
+        }
+
+        reader.seek(SeekFrom::Start(start))?;
+        let mut header = [0u8; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        reader.seek(SeekFrom::Start(start))?;
+
+        let little_score = header_plausibility(&header, true, file_len);
+        let big_score = header_plausibility(&header, false, file_len);
+
+        Ok(little_score >= big_score)
+    }
+}
+
+fn check_section_len(section: &'static str, len: usize, expected: usize) -> crate::Result<()> {
+    match len == expected {
+        true => Ok(()),
+        false => Err(crate::Error::SectionResized {
+            section,
+            len,
+            expected,
+        }),
+    }
+}
+
+fn write_endian<W, T>(writer: &mut W, value: &T, is_little: bool) -> crate::Result<()>
+where
+    W: Write + Seek,
+    T: BinWrite<Args = ()>,
+{
+    match is_little {
+        true => writer.write_le(value)?,
+        false => writer.write_be(value)?,
+    }
+    Ok(())
+}
+
+/// Write the items of a section at the offset it was read from, empty sections aren't written
+fn write_section<W, T>(
+    writer: &mut W,
+    offset: u32,
+    items: &[T],
+    is_little: bool,
+) -> crate::Result<()>
+where
+    W: Write + Seek,
+    T: BinWrite<Args = ()>,
+{
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    writer.seek(SeekFrom::Start(offset as u64))?;
+    for item in items {
+        write_endian(writer, item, is_little)?;
+    }
+    Ok(())
+}
+
+/// Count the checks the fixed ape header passes when read in the given byte order
+///
+/// Counts are single bytes and read the same either way, so only floats and pointers tell the orders apart.
+fn header_plausibility(header: &[u8; HEADER_SIZE], is_little: bool, file_len: u64) -> usize {
+    let read_u32 = |pos: usize| {
+        let bytes = header[pos..pos + 4].try_into().unwrap();
+        match is_little {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        }
+    };
+    let read_f32 = |pos: usize| f32::from_bits(read_u32(pos));
+    // Byte swapped floats tend to end up huge, tiny or not a number at all
+    let is_sane = |value: f32| value == 0.0 || (1.0e-6..1.0e7).contains(&value.abs());
+
+    let mut score = 0;
+
+    // Bounding sphere radius, then its center and the bounding box
+    let radius = read_f32(16);
+    if is_sane(radius) && radius >= 0.0 {
+        score += 1;
+    }
+    let bounds = (20..56).step_by(4).map(read_f32).collect::<Vec<_>>();
+    score += bounds.iter().filter(|value| is_sane(**value)).count();
+    if (0..3).all(|axis| bounds[3 + axis] <= bounds[6 + axis]) {
+        score += 1;
+    }
+
+    // Load distances
+    score += (72..104)
+        .step_by(4)
+        .map(read_f32)
+        .filter(|distance| is_sane(*distance) && *distance >= 0.0)
+        .count();
+
+    // Pointers to the segments, bones, lights and materials have to point inside the file when used
+    let counts = [header[63], header[62], header[67], header[68]];
+    for (pos, count) in [104, 108, 112, 120].into_iter().zip(counts) {
+        let offset = read_u32(pos) as u64;
+        if (count == 0 && offset == 0) || (offset > 0 && offset < file_len) {
+            score += 1;
+        }
+    }
+
+    // The remaining pointers are either unused or inside the file
+    for pos in [116, 124, 128, 132] {
+        if (read_u32(pos) as u64) < file_len {
+            score += 1;
+        }
+    }
+
+    score
+}
+
+#[derive(BinRead, BinWrite, Debug)]
 pub struct FLightInit {
-    #[br(count = 16, map = crate::util::vec_to_null_terminated_str)]
-    pub name: String,
+    pub name: ApeName<16>,
 
-    #[br(count = 16, map = crate::util::vec_to_null_terminated_str)]
-    pub per_pixel_texture_name: String,
-    #[br(count = 16, map = crate::util::vec_to_null_terminated_str)]
-    pub corona_texture_name: String,
+    pub per_pixel_texture_name: ApeName<16>,
+    pub corona_texture_name: ApeName<16>,
 
-    pub flags: u32,
+    pub flags: LightFlags,
 
     pub light_id: u16,
-    pub kind: u8,
+    #[br(map = LightKind::from_u8)]
+    #[bw(map = |kind: &LightKind| kind.to_u8())]
+    pub kind: LightKind,
     pub parent_bone_idx: i8,
 
     pub intensity: f32,
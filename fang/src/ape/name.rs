@@ -0,0 +1,77 @@
+use binrw::{BinRead, BinWrite};
+use serde::{Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt::{Debug, Display};
+
+/// Null terminated name in a fixed size buffer
+///
+/// The whole buffer is kept as it was read, including whatever follows the terminator, so writing
+/// the name back doesn't change any bytes. Names aren't always valid UTF-8, the string form replaces
+/// what isn't.
+#[derive(BinRead, BinWrite, Clone, PartialEq, Eq)]
+pub struct ApeName<const T: usize>(pub [u8; T]);
+
+impl<const T: usize> ApeName<T> {
+    /// Bytes of the name up to its terminator
+    pub fn as_bytes(&self) -> &[u8] {
+        let len = self.0.iter().position(|b| *b == 0).unwrap_or(T);
+        &self.0[..len]
+    }
+
+    pub fn to_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.as_bytes())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.as_bytes().is_empty()
+    }
+
+    /// Replace the name, leaving the bytes after the new terminator as they were
+    ///
+    /// Names that don't fit with their terminator are cut short.
+    pub fn set(&mut self, name: &str) {
+        let len = name.len().min(T.saturating_sub(1));
+        self.0[..len].copy_from_slice(&name.as_bytes()[..len]);
+        if len < T {
+            self.0[len] = 0;
+        }
+    }
+}
+
+impl<const T: usize> Display for ApeName<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&self.to_str())
+    }
+}
+
+impl<const T: usize> Debug for ApeName<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.to_str(), f)
+    }
+}
+
+impl<const T: usize> Default for ApeName<T> {
+    fn default() -> Self {
+        ApeName([0u8; T])
+    }
+}
+
+impl<const T: usize> From<&str> for ApeName<T> {
+    fn from(name: &str) -> Self {
+        let mut new_name = Self::default();
+        new_name.set(name);
+        new_name
+    }
+}
+
+impl<const T: usize> PartialEq<&str> for ApeName<T> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<const T: usize> Serialize for ApeName<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_str())
+    }
+}
@@ -0,0 +1,222 @@
+use serde::Deserialize;
+
+use crate::{Error, Result};
+
+use super::Ape;
+
+/// Property changes to apply to an ape, usually read from a JSON file
+///
+/// Everything is optional, only the given properties are changed. Bones and lights are picked by index
+/// or by name, materials have no names so they're picked by index.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ApePatch {
+    /// Replace all load distances
+    #[serde(default)]
+    pub load_distances: Option<[f32; 8]>,
+    /// Multiply all load distances, after replacing them
+    #[serde(default)]
+    pub load_distance_scale: Option<f32>,
+    #[serde(default)]
+    pub bones: Vec<ApeBonePatch>,
+    #[serde(default)]
+    pub lights: Vec<ApeLightPatch>,
+    #[serde(default)]
+    pub materials: Vec<ApeMaterialPatch>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ApeBonePatch {
+    pub key: ApeItemKey,
+    /// New name of the bone
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ApeLightPatch {
+    pub key: ApeItemKey,
+    /// New name of the light
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub intensity: Option<f32>,
+    /// Red, green, blue and alpha of the light's motif
+    #[serde(default)]
+    pub color: Option<[f32; 4]>,
+    #[serde(default)]
+    pub motif_index: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ApeMaterialPatch {
+    pub index: usize,
+    /// Red, green and blue of the material tint
+    #[serde(default)]
+    pub tint: Option<[f32; 3]>,
+}
+
+/// Picks a bone or light by its index or its name, names are matched ignoring case
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ApeItemKey {
+    Index(usize),
+    Name(String),
+}
+
+impl std::fmt::Display for ApeItemKey {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApeItemKey::Index(index) => write!(fmt, "#{}", index),
+            ApeItemKey::Name(name) => write!(fmt, "{:?}", name),
+        }
+    }
+}
+
+/// Longest bone name that fits with its null terminator
+const MAX_BONE_NAME_LEN: usize = 31;
+/// Longest light name that fits with its null terminator
+const MAX_LIGHT_NAME_LEN: usize = 15;
+
+impl ApePatch {
+    /// Apply the patch, leaving the ape unchanged if any part of it is invalid
+    pub fn apply(&self, ape: &mut Ape) -> Result<()> {
+        // Resolve and check everything before changing anything
+        let bone_names = ape
+            .bones
+            .iter()
+            .map(|bone| bone.name.to_string())
+            .collect::<Vec<_>>();
+        let light_names = ape
+            .lights
+            .iter()
+            .map(|light| light.name.to_string())
+            .collect::<Vec<_>>();
+
+        if let Some(load_distances) = &self.load_distances {
+            for distance in load_distances {
+                check_value("load_distances", *distance, 0.0)?;
+            }
+        }
+        if let Some(scale) = self.load_distance_scale {
+            check_value("load_distance_scale", scale, 0.0)?;
+        }
+        // Check the load distances as they'll end up, scaling can still overflow to infinity
+        let changes_load_distances =
+            self.load_distances.is_some() || self.load_distance_scale.is_some();
+        let load_distances = self.load_distances.unwrap_or(ape.load_distances);
+        let scale = self.load_distance_scale.unwrap_or(1.0);
+        if changes_load_distances {
+            for distance in load_distances {
+                check_value("load_distances", distance * scale, 0.0)?;
+            }
+        }
+
+        let mut bone_indices = Vec::with_capacity(self.bones.len());
+        for bone_patch in &self.bones {
+            bone_indices.push(resolve_key("bone", &bone_patch.key, &bone_names)?);
+            if let Some(name) = &bone_patch.name {
+                check_name(name, MAX_BONE_NAME_LEN)?;
+            }
+        }
+
+        let mut light_indices = Vec::with_capacity(self.lights.len());
+        for light_patch in &self.lights {
+            light_indices.push(resolve_key("light", &light_patch.key, &light_names)?);
+            if let Some(name) = &light_patch.name {
+                check_name(name, MAX_LIGHT_NAME_LEN)?;
+            }
+            if let Some(intensity) = light_patch.intensity {
+                check_value("intensity", intensity, f32::MIN)?;
+            }
+            for component in light_patch.color.iter().flatten() {
+                check_value("color", *component, 0.0)?;
+            }
+        }
+
+        for material_patch in &self.materials {
+            if material_patch.index >= ape.materials.len() {
+                return Err(Error::ApeItemNotFound {
+                    kind: "material",
+                    key: ApeItemKey::Index(material_patch.index).to_string(),
+                });
+            }
+            for component in material_patch.tint.iter().flatten() {
+                check_value("tint", *component, 0.0)?;
+            }
+        }
+
+        // Everything checks out, apply it
+        if changes_load_distances {
+            ape.load_distances = load_distances.map(|distance| distance * scale);
+        }
+
+        for (bone_patch, index) in self.bones.iter().zip(bone_indices) {
+            if let Some(name) = &bone_patch.name {
+                ape.bones[index].name.set(name);
+            }
+        }
+
+        for (light_patch, index) in self.lights.iter().zip(light_indices) {
+            let light = &mut ape.lights[index];
+            if let Some(name) = &light_patch.name {
+                light.name.set(name);
+            }
+            if let Some(intensity) = light_patch.intensity {
+                light.intensity = intensity;
+            }
+            if let Some([red, green, blue, alpha]) = light_patch.color {
+                light.motif.red = red;
+                light.motif.green = green;
+                light.motif.blue = blue;
+                light.motif.alpha = alpha;
+            }
+            if let Some(motif_index) = light_patch.motif_index {
+                light.motif.motif_index = motif_index;
+            }
+        }
+
+        for material_patch in &self.materials {
+            if let Some([red, green, blue]) = material_patch.tint {
+                let tint = &mut ape.materials[material_patch.index].material_tint;
+                tint.red = red;
+                tint.green = green;
+                tint.blue = blue;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn resolve_key(kind: &'static str, key: &ApeItemKey, names: &[String]) -> Result<usize> {
+    let index = match key {
+        ApeItemKey::Index(index) => Some(*index).filter(|index| *index < names.len()),
+        ApeItemKey::Name(name) => names.iter().position(|n| n.eq_ignore_ascii_case(name)),
+    };
+
+    index.ok_or_else(|| Error::ApeItemNotFound {
+        kind,
+        key: key.to_string(),
+    })
+}
+
+fn check_name(name: &str, max_len: usize) -> Result<()> {
+    match name.len() <= max_len {
+        true => Ok(()),
+        false => Err(Error::ApeNameTooLong {
+            name: name.to_string(),
+            max_len,
+        }),
+    }
+}
+
+fn check_value(field: &'static str, value: f32, min: f32) -> Result<()> {
+    match value.is_finite() && value >= min {
+        true => Ok(()),
+        false => Err(Error::InvalidApeValue { field, value }),
+    }
+}
@@ -0,0 +1,318 @@
+use std::fmt::Write;
+
+use super::{geometry::CFMtx43A, Ape};
+
+/// Largest difference from the identity at which two rest matrices still count as inverses
+const INVERSE_TOLERANCE: f32 = 1.0e-3;
+
+/// Something about the skeleton of an ape that doesn't add up
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkeletonIssue {
+    /// The root bone index from the header isn't a bone
+    RootOutOfRange {
+        root_bone_index: u8,
+        bone_count: usize,
+    },
+    /// A bone's parent index isn't a bone, without being the "no parent" value
+    ParentOutOfRange { bone: usize, parent: u8 },
+    /// A bone's child array runs past the end of the skeleton indices
+    ChildrenOutOfRange {
+        bone: usize,
+        start: usize,
+        count: usize,
+        len: usize,
+    },
+    /// A bone lists a child that names another bone as its parent, or isn't a bone at all
+    ChildMismatch { bone: usize, child: u8 },
+    /// A bone names a parent that doesn't list it as a child
+    MissingChild { bone: usize, parent: usize },
+    /// Following the parents of a bone leads back to the bone
+    Cycle { bone: usize },
+    /// The rest bone to model and model to bone matrices don't multiply to the identity
+    NotInverse { bone: usize, error: f32 },
+}
+
+impl std::fmt::Display for SkeletonIssue {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkeletonIssue::RootOutOfRange {
+                root_bone_index,
+                bone_count,
+            } => write!(
+                fmt,
+                "root bone index {} is not one of the {} bones",
+                root_bone_index, bone_count
+            ),
+            SkeletonIssue::ParentOutOfRange { bone, parent } => {
+                write!(
+                    fmt,
+                    "bone {} has parent {} which is not a bone",
+                    bone, parent
+                )
+            }
+            SkeletonIssue::ChildrenOutOfRange {
+                bone,
+                start,
+                count,
+                len,
+            } => write!(
+                fmt,
+                "bone {} has children {}..{} past the end of the {} skeleton indices",
+                bone,
+                start,
+                start + count,
+                len
+            ),
+            SkeletonIssue::ChildMismatch { bone, child } => write!(
+                fmt,
+                "bone {} lists {} as a child, which doesn't have it as its parent",
+                bone, child
+            ),
+            SkeletonIssue::MissingChild { bone, parent } => write!(
+                fmt,
+                "bone {} has parent {} which doesn't list it as a child",
+                bone, parent
+            ),
+            SkeletonIssue::Cycle { bone } => write!(fmt, "bone {} is its own ancestor", bone),
+            SkeletonIssue::NotInverse { bone, error } => write!(
+                fmt,
+                "rest matrices of bone {} are not inverses, off by {}",
+                bone, error
+            ),
+        }
+    }
+}
+
+impl Ape {
+    /// Parent of every bone, None for bones without a valid parent
+    pub fn bone_parents(&self) -> Vec<Option<usize>> {
+        let bone_count = self.bones.len();
+        self.bones
+            .iter()
+            .map(|bone| bone.skeleton.parent_bone_index as usize)
+            .map(|parent| Some(parent).filter(|parent| *parent < bone_count))
+            .collect()
+    }
+
+    /// Bones without a valid parent, and the bones below them in depth first order with their depth
+    ///
+    /// Bones are linked through their parent index. Bones whose parents go around in a cycle are left out.
+    pub fn bone_tree(&self) -> Vec<(usize, usize)> {
+        let parents = self.bone_parents();
+
+        let mut children = vec![Vec::new(); parents.len()];
+        let mut roots = Vec::new();
+        for (bone, parent) in parents.iter().enumerate() {
+            match parent {
+                Some(parent) if *parent != bone => children[*parent].push(bone),
+                _ => roots.push(bone),
+            }
+        }
+
+        let mut tree = Vec::with_capacity(parents.len());
+        let mut stack = roots
+            .into_iter()
+            .rev()
+            .map(|bone| (bone, 0))
+            .collect::<Vec<_>>();
+        while let Some((bone, depth)) = stack.pop() {
+            tree.push((bone, depth));
+            stack.extend(children[bone].iter().rev().map(|child| (*child, depth + 1)));
+        }
+
+        tree
+    }
+
+    /// Check the skeleton for inconsistencies between the header, parents, child arrays and rest matrices
+    pub fn validate_skeleton(&self) -> Vec<SkeletonIssue> {
+        let bone_count = self.bones.len();
+        let parents = self.bone_parents();
+        let mut issues = Vec::new();
+
+        if bone_count > 0 && self.root_bone_index as usize >= bone_count {
+            issues.push(SkeletonIssue::RootOutOfRange {
+                root_bone_index: self.root_bone_index,
+                bone_count,
+            });
+        }
+
+        for (bone_index, bone) in self.bones.iter().enumerate() {
+            let skeleton = &bone.skeleton;
+
+            // 255 is used for bones without a parent
+            if parents[bone_index].is_none() && skeleton.parent_bone_index != u8::MAX {
+                issues.push(SkeletonIssue::ParentOutOfRange {
+                    bone: bone_index,
+                    parent: skeleton.parent_bone_index,
+                });
+            }
+
+            match self.bone_children(bone_index) {
+                Some(children) => {
+                    for child in children {
+                        if parents.get(*child as usize) != Some(&Some(bone_index)) {
+                            issues.push(SkeletonIssue::ChildMismatch {
+                                bone: bone_index,
+                                child: *child,
+                            });
+                        }
+                    }
+                }
+                None => issues.push(SkeletonIssue::ChildrenOutOfRange {
+                    bone: bone_index,
+                    start: skeleton.child_array_start_index as usize,
+                    count: skeleton.child_bone_count as usize,
+                    len: self.skeleton_indices.len(),
+                }),
+            }
+
+            if let Some(parent) = parents[bone_index] {
+                let is_listed = self
+                    .bone_children(parent)
+                    .map_or(false, |siblings| siblings.contains(&(bone_index as u8)));
+                if !is_listed {
+                    issues.push(SkeletonIssue::MissingChild {
+                        bone: bone_index,
+                        parent,
+                    });
+                }
+            }
+
+            // Walking up more bones than there are means going around in circles
+            let mut ancestor = parents[bone_index];
+            for _ in 0..bone_count {
+                match ancestor {
+                    Some(a) if a == bone_index => {
+                        issues.push(SkeletonIssue::Cycle { bone: bone_index });
+                        break;
+                    }
+                    Some(a) => ancestor = parents[a],
+                    None => break,
+                }
+            }
+
+            let error = identity_error(
+                &bone.at_rest_bone_to_model_mtx,
+                &bone.at_rest_model_to_bone_mtx,
+            );
+            if error.is_nan() || error > INVERSE_TOLERANCE {
+                issues.push(SkeletonIssue::NotInverse {
+                    bone: bone_index,
+                    error,
+                });
+            }
+        }
+
+        issues
+    }
+
+    /// Write the skeleton in its rest pose as BVH
+    ///
+    /// BVH joints only have an offset from their parent in model space, the difference between the
+    /// positions of the bone and its parent, so the rest orientations of the bones are lost. Every bone
+    /// hangs below a root joint named after the model.
+    pub fn skeleton_to_bvh(&self) -> String {
+        let mut bvh = String::new();
+        let tree = self.bone_tree();
+        let parents = self.bone_parents();
+
+        writeln!(bvh, "HIERARCHY").unwrap();
+        writeln!(bvh, "ROOT {}", bvh_name(&self.name.to_str())).unwrap();
+        writeln!(bvh, "{{").unwrap();
+        writeln!(bvh, "\tOFFSET 0.000000 0.000000 0.000000").unwrap();
+        writeln!(
+            bvh,
+            "\tCHANNELS 6 Xposition Yposition Zposition Zrotation Xrotation Yrotation"
+        )
+        .unwrap();
+
+        // Every joint needs at least one child, a model without bones only has the root
+        if tree.is_empty() {
+            writeln!(bvh, "\tEnd Site").unwrap();
+            writeln!(bvh, "\t{{").unwrap();
+            writeln!(bvh, "\t\tOFFSET 0.000000 0.000000 0.000000").unwrap();
+            writeln!(bvh, "\t}}").unwrap();
+        }
+
+        for (position, (bone_index, depth)) in tree.iter().enumerate() {
+            let bone = &self.bones[*bone_index];
+            let indent = "\t".repeat(depth + 1);
+            // Root bones hang below the model's origin
+            let offset = match parents[*bone_index] {
+                Some(parent) if parent != *bone_index => {
+                    bone.at_rest_bone_to_model_mtx.p.xyz()
+                        - self.bones[parent].at_rest_bone_to_model_mtx.p.xyz()
+                }
+                _ => bone.at_rest_bone_to_model_mtx.p.xyz(),
+            };
+
+            writeln!(bvh, "{}JOINT {}", indent, bvh_name(&bone.name.to_str())).unwrap();
+            writeln!(bvh, "{}{{", indent).unwrap();
+            writeln!(
+                bvh,
+                "{}\tOFFSET {:.6} {:.6} {:.6}",
+                indent, offset.x, offset.y, offset.z
+            )
+            .unwrap();
+            writeln!(bvh, "{}\tCHANNELS 3 Zrotation Xrotation Yrotation", indent).unwrap();
+
+            // Close this joint and any parents whose children all came before the next joint
+            let next_depth = tree.get(position + 1).map_or(0, |(_, depth)| *depth);
+            if next_depth <= *depth {
+                writeln!(bvh, "{}\tEnd Site", indent).unwrap();
+                writeln!(bvh, "{}\t{{", indent).unwrap();
+                writeln!(bvh, "{}\t\tOFFSET 0.000000 0.000000 0.000000", indent).unwrap();
+                writeln!(bvh, "{}\t}}", indent).unwrap();
+                for closed_depth in (next_depth..=*depth).rev() {
+                    writeln!(bvh, "{}}}", "\t".repeat(closed_depth + 1)).unwrap();
+                }
+            }
+        }
+
+        writeln!(bvh, "}}").unwrap();
+
+        // A single frame of the rest pose
+        let channel_count = 6 + 3 * tree.len();
+        writeln!(bvh, "MOTION").unwrap();
+        writeln!(bvh, "Frames: 1").unwrap();
+        writeln!(bvh, "Frame Time: 0.033333").unwrap();
+        writeln!(bvh, "{}", vec!["0.000000"; channel_count].join(" ")).unwrap();
+
+        bvh
+    }
+}
+
+/// BVH names end at whitespace
+fn bvh_name(name: &str) -> String {
+    match name.is_empty() {
+        true => "unnamed".to_string(),
+        false => name.replace(char::is_whitespace, "_"),
+    }
+}
+
+/// Largest difference between the product of two matrices and the identity
+///
+/// Differences in translation are relative to the translation of the first matrix, to allow for the
+/// precision of floats far from the origin.
+fn identity_error(a: &CFMtx43A, b: &CFMtx43A) -> f32 {
+    let product = *a * *b;
+    let translation_scale = 1.0f32.max(a.p.x.abs()).max(a.p.y.abs()).max(a.p.z.abs());
+
+    let differences = [
+        product.x.xyz() - CFMtx43A::IDENTITY.x.xyz(),
+        product.y.xyz() - CFMtx43A::IDENTITY.y.xyz(),
+        product.z.xyz() - CFMtx43A::IDENTITY.z.xyz(),
+        product.p.xyz() * (1.0 / translation_scale),
+    ];
+
+    let mut error = 0.0f32;
+    for difference in differences {
+        for value in [difference.x, difference.y, difference.z] {
+            if value.is_nan() {
+                return f32::NAN;
+            }
+            error = error.max(value.abs());
+        }
+    }
+    error
+}
@@ -0,0 +1,186 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use thiserror::Error;
+
+use crate::mst::{MstVersion, MstVersionKnown};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong while reading or writing fang files
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(
+        "{}.{}.{} is not a known Mst version",
+        .version.major(),
+        .version.minor(),
+        .version.patch()
+    )]
+    UnknownVersion { version: MstVersion },
+
+    #[error("{version:?} does not represent a known Mst platform")]
+    UnknownPlatform { version: MstVersion },
+
+    #[error("bad magic at {pos:#x}")]
+    BadMagic { pos: u64 },
+
+    #[error("file ended at {offset:#x}, {expected} more bytes were expected")]
+    Truncated { offset: u64, expected: u64 },
+
+    #[error("{name} ends at {end} which is past the end of the archive ({len})")]
+    EntryOutOfBounds { name: String, end: u64, len: u64 },
+
+    #[error("{name} is not an entry in the archive")]
+    EntryNotFound { name: String },
+
+    #[error("there is no entry at index {index}")]
+    EntryIndexOutOfRange { index: usize },
+
+    #[error("{name} is already an entry in the archive")]
+    DuplicateEntry { name: String },
+
+    #[error(
+        "{name} is {} bytes long, but Mst version {version:?} only allows filenames up to {} bytes",
+        .name.len(),
+        .version.max_filename_len()
+    )]
+    FilenameTooLong {
+        name: String,
+        version: MstVersionKnown,
+    },
+
+    #[error("no free entry slots left to add {name}, the archive has to be rebuilt")]
+    NoFreeEntrySlots { name: String },
+
+    #[error("source of {name} ended after {read} of {expected} bytes")]
+    SourceTooShort {
+        name: String,
+        read: u64,
+        expected: u64,
+    },
+
+    #[error("there is no {kind} {key} in the ape")]
+    ApeItemNotFound { kind: &'static str, key: String },
+
+    #[error("{name} is {} bytes long, but only names up to {max_len} bytes fit", .name.len())]
+    ApeNameTooLong { name: String, max_len: usize },
+
+    #[error("{value} is not a valid {field}")]
+    InvalidApeValue { field: &'static str, value: f32 },
+
+    #[error("ape has {len} {section} but room for {expected}, sections can't be resized")]
+    SectionResized {
+        section: &'static str,
+        len: usize,
+        expected: usize,
+    },
+
+    #[error("{value} is not a known {kind} ({expected})")]
+    UnknownValue {
+        kind: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+
+    #[error("{value} is not a valid timestamp (seconds since the epoch, YYYY-MM-DD or RFC 3339)")]
+    InvalidTimestamp { value: String },
+
+    #[error(transparent)]
+    InvalidGlob(#[from] glob::PatternError),
+
+    #[error(transparent)]
+    InvalidRegex(#[from] regex::Error),
+
+    #[error(transparent)]
+    Io(std::io::Error),
+
+    #[error(transparent)]
+    Binrw(binrw::Error),
+}
+
+impl From<binrw::Error> for Error {
+    fn from(error: binrw::Error) -> Self {
+        match error {
+            binrw::Error::BadMagic { pos, .. } => Error::BadMagic { pos },
+            // Enums told apart only by their magic, like MstIdentifier, report a bad magic this way
+            binrw::Error::NoVariantMatch { pos } => Error::BadMagic { pos },
+            binrw::Error::Io(e) => e.into(),
+            // Asserts raise our own errors, unwrap them again
+            binrw::Error::Custom { pos, err } => match err.downcast::<Error>() {
+                Ok(e) => *e,
+                Err(err) => Error::Binrw(binrw::Error::Custom { pos, err }),
+            },
+            e => Error::Binrw(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        let truncated = error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<TruncatedAt>());
+        match truncated {
+            Some(truncated) => Error::Truncated {
+                offset: truncated.offset,
+                expected: truncated.expected,
+            },
+            None => Error::Io(error),
+        }
+    }
+}
+
+/// Where a reader ran out of data, carried inside an `UnexpectedEof` io error until it becomes
+/// [`Error::Truncated`]
+#[derive(Error, Debug)]
+#[error("file ended at {offset:#x}, {expected} more bytes were expected")]
+pub(crate) struct TruncatedAt {
+    offset: u64,
+    expected: u64,
+}
+
+impl TruncatedAt {
+    pub(crate) fn io_error(offset: u64, expected: u64) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            TruncatedAt { offset, expected },
+        )
+    }
+}
+
+/// Reader that reports where it ran out of data, binrw's own io errors don't say
+///
+/// Running out of data is always an error, so it's only for reading structures of a known size.
+pub(crate) struct TruncationReader<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: Seek> TruncationReader<R> {
+    pub(crate) fn new(mut inner: R) -> Result<Self> {
+        let pos = inner.stream_position()?;
+        Ok(Self { inner, pos })
+    }
+}
+
+impl<R: Read> Read for TruncationReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            return Err(TruncatedAt::io_error(self.pos, buf.len() as u64));
+        }
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for TruncationReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+        Ok(self.pos)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.pos)
+    }
+}
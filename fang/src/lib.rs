@@ -1,5 +1,8 @@
 pub use binrw::{BinReaderExt, BinWriterExt};
 
+mod error;
+pub use error::{Error, Result};
+
 pub mod ape;
 pub mod mst;
 pub mod rdg;
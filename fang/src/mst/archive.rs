@@ -0,0 +1,247 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Seek, SeekFrom},
+};
+
+use binrw::BinReaderExt;
+
+use crate::{
+    error::{TruncatedAt, TruncationReader},
+    Error, Result,
+};
+
+use super::{
+    entry::{CanonicalEntry, CanonicalSupportEntry, Entry},
+    lenient::{read_lenient, MstLayoutGuess},
+    Mst, MstIdentifier, MstPlatformKnown, MstVersionKnown,
+};
+
+/// An Mst with random access to the data of its entries
+pub struct MstArchive<R: Read + Seek> {
+    reader: R,
+    mst: Mst,
+    version: MstVersionKnown,
+    platform: MstPlatformKnown,
+    entries: Vec<CanonicalEntry>,
+    support_entries: Vec<CanonicalSupportEntry>,
+    entry_indices: HashMap<String, usize>,
+    layout_guess: Option<MstLayoutGuess>,
+}
+
+impl<R: Read + Seek> MstArchive<R> {
+    /// Parse the Mst at the start of the reader
+    pub fn new(mut reader: R) -> Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+        let mst = TruncationReader::new(&mut reader)?.read_le::<Mst>()?;
+
+        let version = MstVersionKnown::try_from(mst.body.version())?;
+        let platform = MstPlatformKnown::try_from(mst.body.version())?;
+
+        Ok(Self::from_parts(reader, mst, version, platform, None))
+    }
+
+    /// Parse the Mst at the start of the reader, guessing the entry layout if its version or platform is unknown
+    ///
+    /// An archive read this way can't be written back if a guess was made, see [`MstArchive::layout_guess`].
+    pub fn new_lenient(mut reader: R) -> Result<Self> {
+        let (mst, layout_guess) = read_lenient(&mut reader)?;
+
+        let (version, platform) = match &layout_guess {
+            Some(guess) => (guess.layout, guess.platform),
+            None => (
+                MstVersionKnown::try_from(mst.body.version())?,
+                MstPlatformKnown::try_from(mst.body.version())?,
+            ),
+        };
+
+        Ok(Self::from_parts(
+            reader,
+            mst,
+            version,
+            platform,
+            layout_guess,
+        ))
+    }
+
+    fn from_parts(
+        reader: R,
+        mst: Mst,
+        version: MstVersionKnown,
+        platform: MstPlatformKnown,
+        layout_guess: Option<MstLayoutGuess>,
+    ) -> Self {
+        let entries = mst.collect_entries();
+        let support_entries = mst.collect_support_entries();
+        let entry_indices = entry_indices(&entries);
+
+        Self {
+            reader,
+            mst,
+            version,
+            platform,
+            entries,
+            support_entries,
+            entry_indices,
+            layout_guess,
+        }
+    }
+
+    pub fn mst(&self) -> &Mst {
+        &self.mst
+    }
+
+    pub fn identifier(&self) -> MstIdentifier {
+        self.mst.identifier
+    }
+
+    pub fn version(&self) -> MstVersionKnown {
+        self.version
+    }
+
+    pub fn platform(&self) -> MstPlatformKnown {
+        self.platform
+    }
+
+    /// The guess made by [`MstArchive::new_lenient`], if the version or platform wasn't known
+    pub fn layout_guess(&self) -> Option<&MstLayoutGuess> {
+        self.layout_guess.as_ref()
+    }
+
+    pub fn entries(&self) -> std::slice::Iter<'_, CanonicalEntry> {
+        self.entries.iter()
+    }
+
+    pub fn support_entries(&self) -> std::slice::Iter<'_, CanonicalSupportEntry> {
+        self.support_entries.iter()
+    }
+
+    /// Look up an entry by name, ignoring case like the engine does
+    pub fn entry(&self, name: &str) -> Option<&CanonicalEntry> {
+        self.entry_index(name).map(|index| &self.entries[index])
+    }
+
+    /// Index of an entry by name, ignoring case like the engine does
+    pub fn entry_index(&self, name: &str) -> Option<usize> {
+        self.entry_indices.get(&name.to_ascii_uppercase()).copied()
+    }
+
+    /// Open the data of an entry by name
+    pub fn open_entry(&mut self, name: &str) -> Result<MstEntryReader<'_, R>> {
+        match self.entry_index(name) {
+            Some(index) => self.open_entry_at(index),
+            None => Err(Error::EntryNotFound {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Open the data of the entry at the specified index in the entry table
+    pub fn open_entry_at(&mut self, index: usize) -> Result<MstEntryReader<'_, R>> {
+        let entry = match self.entries.get(index) {
+            Some(entry) => entry,
+            None => return Err(Error::EntryIndexOutOfRange { index }),
+        };
+
+        let offset = entry.offset() as u64;
+        let size = entry.size() as u64;
+        let bytes_in_file = self.mst.body.header.bytes_in_file as u64;
+        if offset + size > bytes_in_file {
+            return Err(Error::EntryOutOfBounds {
+                name: entry.filename(),
+                end: offset + size,
+                len: bytes_in_file,
+            });
+        }
+
+        self.reader.seek(SeekFrom::Start(offset))?;
+
+        Ok(MstEntryReader {
+            reader: &mut self.reader,
+            offset,
+            size,
+            pos: 0,
+        })
+    }
+
+    /// Read the whole data of an entry by name
+    pub fn read_entry(&mut self, name: &str) -> Result<Vec<u8>> {
+        let mut entry_reader = self.open_entry(name)?;
+        let mut data = Vec::with_capacity(entry_reader.len() as usize);
+        entry_reader.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Map uppercase entry names to their index, the first entry wins if a name appears more than once
+pub(crate) fn entry_indices(entries: &[CanonicalEntry]) -> HashMap<String, usize> {
+    entries
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, entry)| (entry.filename().to_ascii_uppercase(), index))
+        .collect()
+}
+
+/// Bounded view of the data of a single entry
+pub struct MstEntryReader<'a, R: Read + Seek> {
+    reader: &'a mut R,
+    offset: u64,
+    size: u64,
+    pos: u64,
+}
+
+impl<'a, R: Read + Seek> MstEntryReader<'a, R> {
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+impl<'a, R: Read + Seek> Read for MstEntryReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.pos);
+        let len = (buf.len() as u64).min(remaining) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let read = self.reader.read(&mut buf[..len])?;
+        if read == 0 {
+            // The archive is shorter than its entry table says, don't hand out a silently cut entry
+            return Err(TruncatedAt::io_error(
+                self.offset + self.pos,
+                self.size - self.pos,
+            ));
+        }
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<'a, R: Read + Seek> Seek for MstEntryReader<'a, R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => pos as i64,
+            SeekFrom::End(delta) => self.size as i64 + delta,
+            SeekFrom::Current(delta) => self.pos as i64 + delta,
+        };
+
+        if new_pos < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            ));
+        }
+
+        self.pos = new_pos as u64;
+        self.reader.seek(SeekFrom::Start(self.offset + self.pos))?;
+        Ok(self.pos)
+    }
+}
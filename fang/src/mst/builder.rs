@@ -4,14 +4,21 @@ use std::{
     fs::File,
     io::{BufReader, Read, Seek, SeekFrom, Write},
     rc::Rc,
+    str::FromStr,
 };
 
 use binrw::BinWriterExt;
 use chrono::Utc;
 
+use crate::{Error, Result};
+
 use super::{
+    crc::CrcWriter,
     entries::{Entries, InnerEntries},
-    entry::{EntryV160, EntryV170, EntryV180Variable},
+    entry::{
+        cmp_filenames, CanonicalSupportEntry, Entry, EntryV160, EntryV170, EntryV180Variable,
+        SupportEntry,
+    },
     header::{MstCompilers, MstHeader},
     Mst, MstBody, MstIdentifier, MstPlatformKnown, MstVersion, MstVersionKnown,
 };
@@ -21,7 +28,17 @@ pub struct MstBuilder {
     platform: MstPlatformKnown,
     version: MstVersionKnown,
     compilers: MstCompilers,
-    entry_sources: HashMap<String, MstBuilderEntrySource>,
+    entry_sources: Vec<(String, MstBuilderEntrySource)>,
+    entry_indices: HashMap<String, usize>,
+    sources: Vec<MstBuilderSource>,
+    source_paths: HashMap<String, MstBuilderSourceId>,
+    order: MstBuilderOrder,
+    support_entries: Vec<CanonicalSupportEntry>,
+    num_free_entries: usize,
+    num_free_support_entries: usize,
+    write_crcs: bool,
+    kept_crcs: HashMap<String, u32>,
+    kept_flags: HashMap<String, u16>,
 }
 
 impl MstBuilder {
@@ -34,17 +51,37 @@ impl MstBuilder {
             platform,
             version,
             compilers,
-            entry_sources: Default::default(),
+            entry_sources: Vec::new(),
+            entry_indices: Default::default(),
+            sources: Vec::new(),
+            source_paths: Default::default(),
+            order: MstBuilderOrder::Insertion,
+            support_entries: Vec::new(),
+            num_free_entries: 0,
+            num_free_support_entries: 0,
+            write_crcs: false,
+            kept_crcs: Default::default(),
+            kept_flags: Default::default(),
         }
     }
 
     /// Create a builder with no entries but with versions and platform copied from an existing Mst
-    pub fn from_mst_empty(mst: &Mst) -> anyhow::Result<Self> {
+    pub fn from_mst_empty(mst: &Mst) -> Result<Self> {
         Ok(Self {
             platform: MstPlatformKnown::try_from(mst.body.version())?,
             version: MstVersionKnown::try_from(mst.body.version())?,
             compilers: mst.body.header.compilers,
-            entry_sources: Default::default(),
+            entry_sources: Vec::new(),
+            entry_indices: Default::default(),
+            sources: Vec::new(),
+            source_paths: Default::default(),
+            order: MstBuilderOrder::Insertion,
+            support_entries: Vec::new(),
+            num_free_entries: 0,
+            num_free_support_entries: 0,
+            write_crcs: false,
+            kept_crcs: Default::default(),
+            kept_flags: Default::default(),
         })
     }
 
@@ -53,18 +90,24 @@ impl MstBuilder {
         self.version = *version;
     }
 
+    /// Update the order the entries are written in
+    pub fn set_order(&mut self, order: MstBuilderOrder) {
+        self.order = order;
+    }
+
     /// Check if an entry by the specified path has already been added
     pub fn has_entry(&self, path: String) -> bool {
-        self.entry_sources.contains_key(&path)
+        self.entry_indices.contains_key(&path)
     }
 
     /// Add an entry from memory
     pub fn add_entry_memory(&mut self, path: String, data: Vec<u8>, timestamp: Option<u32>) {
-        self.entry_sources
-            .insert(path, MstBuilderEntrySource::Memory { data, timestamp });
+        self.insert_entry(path, MstBuilderEntrySource::Memory { data, timestamp });
     }
 
     /// Add an entry that will be read from a file
+    ///
+    /// The file is only opened once the Mst is written, and only once for all entries sharing it
     pub fn add_entry_file(
         &mut self,
         entry_path: String,
@@ -73,10 +116,40 @@ impl MstBuilder {
         size: usize,
         timestamp: Option<u32>,
     ) {
-        self.entry_sources.insert(
+        let source = match self.source_paths.get(&file_path) {
+            Some(source) => *source,
+            None => {
+                let source = MstBuilderSourceId(self.sources.len());
+                self.sources.push(MstBuilderSource::Path(file_path.clone()));
+                self.source_paths.insert(file_path, source);
+                source
+            }
+        };
+
+        self.add_entry_source(entry_path, source, offset, size, timestamp);
+    }
+
+    /// Register a reader that entries can be read from, such as another archive
+    pub fn add_source<R: Read + Seek + 'static>(&mut self, reader: R) -> MstBuilderSourceId {
+        let source = MstBuilderSourceId(self.sources.len());
+        self.sources
+            .push(MstBuilderSource::Reader(Box::new(reader)));
+        source
+    }
+
+    /// Add an entry that will be read from a source registered with [`MstBuilder::add_source`]
+    pub fn add_entry_source(
+        &mut self,
+        entry_path: String,
+        source: MstBuilderSourceId,
+        offset: usize,
+        size: usize,
+        timestamp: Option<u32>,
+    ) {
+        self.insert_entry(
             entry_path,
-            MstBuilderEntrySource::File {
-                path: file_path,
+            MstBuilderEntrySource::Source {
+                source,
                 offset,
                 size,
                 timestamp,
@@ -84,6 +157,99 @@ impl MstBuilder {
         );
     }
 
+    /// Add an entry at the end, or replace the source of an entry added before while keeping its position
+    fn insert_entry(&mut self, path: String, source: MstBuilderEntrySource) {
+        match self.entry_indices.get(&path) {
+            Some(index) => self.entry_sources[*index].1 = source,
+            None => {
+                self.entry_indices
+                    .insert(path.clone(), self.entry_sources.len());
+                self.entry_sources.push((path, source));
+            }
+        }
+    }
+
+    /// Number of entries added so far
+    pub fn entry_count(&self) -> usize {
+        self.entry_sources.len()
+    }
+
+    /// Compute the checksum of every entry's data and write it to the entry table
+    ///
+    /// Off by default, since [`entry_crc`](super::crc::entry_crc) hasn't been confirmed to match the
+    /// engine's checksum. Entries without a checksum kept through [`MstBuilder::keep_entry_crc`] get 0.
+    pub fn set_write_crcs(&mut self, write_crcs: bool) {
+        self.write_crcs = write_crcs;
+    }
+
+    /// Keep the checksum an entry already has, such as when it's copied from another archive
+    ///
+    /// Ignored when checksums are computed with [`MstBuilder::set_write_crcs`].
+    pub fn keep_entry_crc(&mut self, path: &str, crc: u32) {
+        self.kept_crcs.insert(path.to_string(), crc);
+    }
+
+    /// Keep the flags an entry already has, such as when it's copied from another archive
+    ///
+    /// Only V180 archives store entry flags, entries without kept flags get 0.
+    pub fn keep_entry_flags(&mut self, path: &str, flags: u16) {
+        self.kept_flags.insert(path.to_string(), flags);
+    }
+
+    /// Add every entry of an existing Mst as a reference to the file it was read from
+    ///
+    /// The entries keep their timestamps, flags and checksums.
+    pub fn add_mst_entries(&mut self, mst: &Mst, file_path: &str) {
+        for entry in mst.collect_entries() {
+            let path = entry.filename();
+            self.add_entry_file(
+                path.clone(),
+                file_path.to_string(),
+                entry.offset(),
+                entry.size(),
+                Some(entry.timestamp),
+            );
+            self.keep_entry_crc(&path, entry.crc);
+            self.keep_entry_flags(&path, entry.flags);
+        }
+    }
+
+    /// Add every support entry of an existing Mst, replacing any previously added ones with the same path
+    pub fn add_mst_support_entries(&mut self, mst: &Mst) {
+        for support_entry in mst.collect_support_entries() {
+            self.add_support_entry(support_entry);
+        }
+    }
+
+    /// Check if a support entry by the specified path has already been added
+    pub fn has_support_entry(&self, path: String) -> bool {
+        self.support_entries.iter().any(|e| e.filename() == path)
+    }
+
+    /// Add a support entry, replacing any previously added one with the same path
+    pub fn add_support_entry(&mut self, support_entry: CanonicalSupportEntry) {
+        let path = support_entry.filename();
+        match self
+            .support_entries
+            .iter_mut()
+            .find(|e| e.filename() == path)
+        {
+            Some(existing) => *existing = support_entry,
+            None => self.support_entries.push(support_entry),
+        }
+    }
+
+    /// Number of support entries added so far
+    pub fn support_entry_count(&self) -> usize {
+        self.support_entries.len()
+    }
+
+    /// Set how many empty entry and support entry slots to leave for files added later on
+    pub fn set_free_slots(&mut self, num_free_entries: usize, num_free_support_entries: usize) {
+        self.num_free_entries = num_free_entries;
+        self.num_free_support_entries = num_free_support_entries;
+    }
+
     /// Creates the table of entries for the specific target version of Mst
     fn create_entries(&self) -> Entries {
         let timestamp_now = Utc::now().timestamp() as u32;
@@ -96,26 +262,30 @@ impl MstBuilder {
                         .iter()
                         .map(|(path, source)| EntryV180Variable {
                             filename: path.into(),
-                            flags: 0,
+                            flags: self.kept_flags.get(path).copied().unwrap_or(0),
                             offset: 0,
                             size: match source {
-                                MstBuilderEntrySource::File { size, .. } => *size as u32,
+                                MstBuilderEntrySource::Source { size, .. } => *size as u32,
                                 MstBuilderEntrySource::Memory { data, .. } => data.len() as u32,
                             },
                             timestamp: match source {
-                                MstBuilderEntrySource::File { timestamp, .. } => {
+                                MstBuilderEntrySource::Source { timestamp, .. } => {
                                     timestamp.unwrap_or(timestamp_now)
                                 }
                                 MstBuilderEntrySource::Memory { timestamp, .. } => {
                                     timestamp.unwrap_or(timestamp_now)
                                 }
                             },
-                            crc: 0,
+                            crc: self.kept_crcs.get(path).copied().unwrap_or(0),
                         })
                         .collect(),
-                    free_entries: Vec::new(),
-                    support_entries: Vec::new(),
-                    free_support_entries: Vec::new(),
+                    free_entries: vec![Default::default(); self.num_free_entries],
+                    support_entries: self
+                        .support_entries
+                        .iter()
+                        .map(|e| e.clone().into())
+                        .collect(),
+                    free_support_entries: vec![Default::default(); self.num_free_support_entries],
                 },
             },
             MstVersionKnown::V180 => Entries::V180 {
@@ -125,26 +295,30 @@ impl MstBuilder {
                         .iter()
                         .map(|(path, source)| EntryV180Variable {
                             filename: path.into(),
-                            flags: 0,
+                            flags: self.kept_flags.get(path).copied().unwrap_or(0),
                             offset: 0,
                             size: match source {
-                                MstBuilderEntrySource::File { size, .. } => *size as u32,
+                                MstBuilderEntrySource::Source { size, .. } => *size as u32,
                                 MstBuilderEntrySource::Memory { data, .. } => data.len() as u32,
                             },
                             timestamp: match source {
-                                MstBuilderEntrySource::File { timestamp, .. } => {
+                                MstBuilderEntrySource::Source { timestamp, .. } => {
                                     timestamp.unwrap_or(timestamp_now)
                                 }
                                 MstBuilderEntrySource::Memory { timestamp, .. } => {
                                     timestamp.unwrap_or(timestamp_now)
                                 }
                             },
-                            crc: 0,
+                            crc: self.kept_crcs.get(path).copied().unwrap_or(0),
                         })
                         .collect(),
-                    free_entries: Vec::new(),
-                    support_entries: Vec::new(),
-                    free_support_entries: Vec::new(),
+                    free_entries: vec![Default::default(); self.num_free_entries],
+                    support_entries: self
+                        .support_entries
+                        .iter()
+                        .map(|e| e.clone().into())
+                        .collect(),
+                    free_support_entries: vec![Default::default(); self.num_free_support_entries],
                 },
             },
             MstVersionKnown::V170 => Entries::V170 {
@@ -156,23 +330,27 @@ impl MstBuilder {
                             filename: path.into(),
                             offset: 0,
                             size: match source {
-                                MstBuilderEntrySource::File { size, .. } => *size as u32,
+                                MstBuilderEntrySource::Source { size, .. } => *size as u32,
                                 MstBuilderEntrySource::Memory { data, .. } => data.len() as u32,
                             },
                             timestamp: match source {
-                                MstBuilderEntrySource::File { timestamp, .. } => {
+                                MstBuilderEntrySource::Source { timestamp, .. } => {
                                     timestamp.unwrap_or(timestamp_now)
                                 }
                                 MstBuilderEntrySource::Memory { timestamp, .. } => {
                                     timestamp.unwrap_or(timestamp_now)
                                 }
                             },
-                            crc: 0,
+                            crc: self.kept_crcs.get(path).copied().unwrap_or(0),
                         })
                         .collect(),
-                    free_entries: Vec::new(),
-                    support_entries: Vec::new(),
-                    free_support_entries: Vec::new(),
+                    free_entries: vec![Default::default(); self.num_free_entries],
+                    support_entries: self
+                        .support_entries
+                        .iter()
+                        .map(|e| e.clone().into())
+                        .collect(),
+                    free_support_entries: vec![Default::default(); self.num_free_support_entries],
                 },
             },
             MstVersionKnown::V160 => Entries::V160 {
@@ -184,11 +362,11 @@ impl MstBuilder {
                             filename: path.into(),
                             offset: 0,
                             size: match source {
-                                MstBuilderEntrySource::File { size, .. } => *size as u32,
+                                MstBuilderEntrySource::Source { size, .. } => *size as u32,
                                 MstBuilderEntrySource::Memory { data, .. } => data.len() as u32,
                             },
                             timestamp: match source {
-                                MstBuilderEntrySource::File { timestamp, .. } => {
+                                MstBuilderEntrySource::Source { timestamp, .. } => {
                                     timestamp.unwrap_or(timestamp_now)
                                 }
                                 MstBuilderEntrySource::Memory { timestamp, .. } => {
@@ -197,9 +375,13 @@ impl MstBuilder {
                             },
                         })
                         .collect(),
-                    free_entries: Vec::new(),
-                    support_entries: Vec::new(),
-                    free_support_entries: Vec::new(),
+                    free_entries: vec![Default::default(); self.num_free_entries],
+                    support_entries: self
+                        .support_entries
+                        .iter()
+                        .map(|e| e.clone().into())
+                        .collect(),
+                    free_support_entries: vec![Default::default(); self.num_free_support_entries],
                 },
             },
         }
@@ -218,9 +400,9 @@ impl MstBuilder {
                 header: MstHeader {
                     bytes_in_file: Default::default(),
                     num_entries: self.entry_sources.len() as u32,
-                    num_free_entries: 0,
-                    num_support_entries: 0,
-                    num_free_support_entries: 0,
+                    num_free_entries: self.num_free_entries as u32,
+                    num_support_entries: self.support_entries.len() as u32,
+                    num_free_support_entries: self.num_free_support_entries as u32,
                     data_offset: Default::default(),
                     compilers: self.compilers,
                     reserved: Default::default(),
@@ -230,16 +412,40 @@ impl MstBuilder {
         }
     }
 
-    pub fn write<W: Write + Seek>(self, writer: &mut W) -> anyhow::Result<()> {
+    /// Indices of the entries in the order their data should be written
+    fn data_order(&self) -> Vec<usize> {
+        let mut data_order = (0..self.entry_sources.len()).collect::<Vec<_>>();
+
+        // Follow the layout of the source files, with entries from memory going last
+        if self.order == MstBuilderOrder::SourceLayout {
+            data_order.sort_by_key(|index| match &self.entry_sources[*index].1 {
+                MstBuilderEntrySource::Source { source, offset, .. } => (0, source.0, *offset),
+                MstBuilderEntrySource::Memory { .. } => (1, 0, 0),
+            });
+        }
+
+        data_order
+    }
+
+    pub fn write<W: Write + Seek>(mut self, writer: &mut W) -> Result<()> {
+        // Sort the entry table if needed, the stable sort keeps duplicates in insertion order
+        if self.order == MstBuilderOrder::Sorted {
+            self.entry_sources
+                .sort_by(|(a, _), (b, _)| cmp_filenames(a, b));
+        }
+
+        let version = self.version;
         let mst = self.create_mst();
 
         // Write the Mst header with entries to the output file, recording where we should later place the offsets to each entry's data
         let content_offset_offsets = Rc::new(RefCell::new(Vec::new()));
         writer.write_le_args(&mst, (content_offset_offsets.clone(),))?;
 
-        // Write each entry's data to the output file, recording their offsets
-        let mut content_offsets = Vec::new();
-        for (_entry_path, source) in self.entry_sources {
+        // Write each entry's data to the output file, recording their offsets and checksums
+        let mut content_offsets = vec![0u64; self.entry_sources.len()];
+        let mut content_crcs = vec![0u32; self.entry_sources.len()];
+        for index in self.data_order() {
+            let (entry_path, source) = &self.entry_sources[index];
             let mut pos = writer.stream_position()?;
 
             // Align the entry's data to 2048 bytes if necessary
@@ -250,59 +456,62 @@ impl MstBuilder {
                 pos = writer.stream_position()?;
             }
 
-            // Read the entry's data from the source if necessary
-            let content_buf = match source {
-                MstBuilderEntrySource::File {
-                    path: source_path,
+            // Copy the entry's data to the output file, computing its checksum on the way
+            let mut crc_writer = CrcWriter::new(&mut *writer);
+            match source {
+                MstBuilderEntrySource::Source {
+                    source,
                     offset,
                     size,
                     ..
                 } => {
-                    let mut source_file = BufReader::new(File::open(&source_path)?);
-                    source_file.seek(SeekFrom::Start(offset as u64))?;
-                    let mut data = vec![0u8; size];
-                    source_file.read_exact(&mut data)?;
-                    data
+                    let reader = self.sources[source.0].reader()?;
+                    reader.seek(SeekFrom::Start(*offset as u64))?;
+
+                    let copied = std::io::copy(&mut reader.take(*size as u64), &mut crc_writer)?;
+                    if copied < *size as u64 {
+                        return Err(Error::SourceTooShort {
+                            name: entry_path.clone(),
+                            read: copied,
+                            expected: *size as u64,
+                        });
+                    }
                 }
-                MstBuilderEntrySource::Memory { data, .. } => data,
+                MstBuilderEntrySource::Memory { data, .. } => crc_writer.write_all(data)?,
             };
 
-            // Write the data to the output file
-            content_offsets.push(pos);
-            writer.write_all(&content_buf)?;
+            content_offsets[index] = pos;
+            content_crcs[index] = crc_writer.crc();
         }
 
         let file_size = writer.stream_position()?;
+        let is_little = mst.identifier.is_little();
 
         // Write the total size in bytes into the Mst header
         writer.seek(SeekFrom::Start(8))?;
-        let total_size_bytes = file_size as u32;
-        if mst.identifier.is_little() {
-            writer.write_all(&total_size_bytes.to_le_bytes())?;
-        } else {
-            writer.write_all(&total_size_bytes.to_be_bytes())?;
-        }
+        write_u32(writer, file_size as u32, is_little)?;
 
         // Write the data offset into the Mst header
         // If we have entries (as we should), use the lowest offset recorded
         // Otherwise, use the file size, which should be just the length of the header
         writer.seek(SeekFrom::Start(28))?;
         let data_offset = *content_offsets.iter().min().unwrap_or(&file_size) as u32;
-        if mst.identifier.is_little() {
-            writer.write_all(&data_offset.to_le_bytes())?;
-        } else {
-            writer.write_all(&data_offset.to_be_bytes())?;
-        }
+        write_u32(writer, data_offset, is_little)?;
 
-        // Write the data offsets for every entry in the Mst header
-        for (pos, entry_offset) in content_offset_offsets.borrow().iter().zip(content_offsets) {
+        // Write the data offsets and computed checksums for every entry in the Mst header
+        for ((pos, entry_offset), content_crc) in content_offset_offsets
+            .borrow()
+            .iter()
+            .zip(content_offsets)
+            .zip(content_crcs)
+        {
             writer.seek(SeekFrom::Start(*pos))?;
+            write_u32(writer, entry_offset as u32, is_little)?;
 
-            let entry_offset = entry_offset as u32;
-            if mst.identifier.is_little() {
-                writer.write_all(&entry_offset.to_le_bytes())?;
-            } else {
-                writer.write_all(&entry_offset.to_be_bytes())?;
+            // The checksum follows the offset, size and timestamp in versions that have one
+            if self.write_crcs && version != MstVersionKnown::V160 {
+                writer.seek(SeekFrom::Start(*pos + 12))?;
+                write_u32(writer, content_crc, is_little)?;
             }
         }
 
@@ -310,11 +519,85 @@ impl MstBuilder {
     }
 }
 
+fn write_u32<W: Write>(writer: &mut W, value: u32, is_little: bool) -> std::io::Result<()> {
+    if is_little {
+        writer.write_all(&value.to_le_bytes())
+    } else {
+        writer.write_all(&value.to_be_bytes())
+    }
+}
+
+/// Order of the entries in the written Mst
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MstBuilderOrder {
+    /// Entries are written in the order they were first added
+    Insertion,
+    /// Entries are sorted by name the way retail archives are, see [`cmp_filenames`]
+    Sorted,
+    /// The entry table keeps the insertion order, but data is laid out in the order it appears in the source files
+    SourceLayout,
+}
+
+impl FromStr for MstBuilderOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "insertion" => Ok(MstBuilderOrder::Insertion),
+            "sorted" => Ok(MstBuilderOrder::Sorted),
+            "source" => Ok(MstBuilderOrder::SourceLayout),
+            _ => Err(Error::UnknownValue {
+                kind: "entry order",
+                value: s.to_string(),
+                expected: "insertion, sorted, source",
+            }),
+        }
+    }
+}
+
+/// Identifies a source that entries can be read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MstBuilderSourceId(usize);
+
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+enum MstBuilderSource {
+    /// A file that will be opened when the Mst is written
+    Path(String),
+    /// An open reader
+    Reader(Box<dyn ReadSeek>),
+}
+
+impl MstBuilderSource {
+    /// Get the reader for this source, opening the file on first use
+    fn reader(&mut self) -> std::io::Result<&mut dyn ReadSeek> {
+        if let MstBuilderSource::Path(path) = self {
+            *self = MstBuilderSource::Reader(Box::new(BufReader::new(File::open(path)?)));
+        }
+
+        match self {
+            MstBuilderSource::Reader(reader) => Ok(reader.as_mut()),
+            MstBuilderSource::Path(_) => unreachable!(),
+        }
+    }
+}
+
+impl std::fmt::Debug for MstBuilderSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MstBuilderSource::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            MstBuilderSource::Reader(_) => f.debug_tuple("Reader").finish(),
+        }
+    }
+}
+
 #[derive(Debug)]
 enum MstBuilderEntrySource {
-    /// The entry will be read from a file, at the specified position and amount of bytes
-    File {
-        path: String,
+    /// The entry will be read from a source, at the specified position and amount of bytes
+    Source {
+        source: MstBuilderSourceId,
         offset: usize,
         size: usize,
         timestamp: Option<u32>,
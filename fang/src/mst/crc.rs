@@ -0,0 +1,43 @@
+use std::io::Write;
+
+/// Compute the checksum stored alongside an entry's data in V170 and V180 archives
+///
+/// This is assumed to be the standard CRC-32 (IEEE, as used by zlib). Identical entries in retail archives
+/// carry identical checksums, but none of the sample archives include their data, so the algorithm has not
+/// been confirmed against the engine yet. Until it is, writing and checking checksums is opt-in and
+/// existing checksums are kept as they are.
+pub fn entry_crc(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
+/// Wraps a writer, computing the checksum of everything written through it
+pub struct CrcWriter<W: Write> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: Write> CrcWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    /// Checksum of everything written so far
+    pub fn crc(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+}
+
+impl<W: Write> Write for CrcWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
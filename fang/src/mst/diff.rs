@@ -0,0 +1,255 @@
+use std::io::{Read, Seek};
+
+use serde::Serialize;
+
+use crate::Result;
+
+use super::{
+    archive::{entry_indices, MstArchive},
+    entry::{CanonicalEntry, Entry},
+    Mst, MstVersionKnown,
+};
+
+/// Differences between an old and a new version of an Mst
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MstDiff {
+    /// Header fields whose values differ
+    pub header: Vec<MstHeaderChange>,
+    /// Entries only in the new archive
+    pub added: Vec<CanonicalEntry>,
+    /// Entries only in the old archive
+    pub removed: Vec<CanonicalEntry>,
+    /// Entries in both archives that differ in size, timestamp or content
+    pub changed: Vec<MstEntryChange>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MstHeaderChange {
+    pub field: String,
+    pub old: u32,
+    pub new: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MstEntryChange {
+    pub old: CanonicalEntry,
+    pub new: CanonicalEntry,
+    pub resized: bool,
+    pub retimestamped: bool,
+    pub content: MstContentChange,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MstContentChange {
+    Unchanged,
+    Changed,
+    /// Neither checksums nor data were available to compare
+    Unknown,
+}
+
+/// The parts of an Mst that get compared, so the archives are free to be read from while comparing data
+struct MstDiffSide {
+    header_fields: Vec<(String, u32)>,
+    entries: Vec<CanonicalEntry>,
+    has_crcs: bool,
+}
+
+impl MstDiffSide {
+    fn new(mst: &Mst) -> Result<Self> {
+        let version = MstVersionKnown::try_from(mst.body.version())?;
+
+        Ok(Self {
+            header_fields: header_fields(mst),
+            entries: mst.collect_entries(),
+            has_crcs: version != MstVersionKnown::V160,
+        })
+    }
+}
+
+impl MstDiff {
+    /// Compare the headers and entry tables, content changes are detected from entry checksums
+    pub fn from_tables(old: &Mst, new: &Mst) -> Result<Self> {
+        let old = MstDiffSide::new(old)?;
+        let new = MstDiffSide::new(new)?;
+
+        Self::compare(&old, &new, |_, _| Ok(None))
+    }
+
+    /// Compare the headers, entry tables and entry data
+    ///
+    /// Checksums are used as a fallback for entries whose data is not in both files, like stripped archives.
+    pub fn from_archives<R1: Read + Seek, R2: Read + Seek>(
+        old: &mut MstArchive<R1>,
+        new: &mut MstArchive<R2>,
+    ) -> Result<Self> {
+        let old_side = MstDiffSide::new(old.mst())?;
+        let new_side = MstDiffSide::new(new.mst())?;
+
+        Self::compare(&old_side, &new_side, |old_index, new_index| {
+            let old_data = read_entry_data(old, old_index)?;
+            let new_data = read_entry_data(new, new_index)?;
+
+            Ok(match (old_data, new_data) {
+                (Some(old_data), Some(new_data)) => Some(old_data != new_data),
+                _ => None,
+            })
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+    }
+
+    /// Compare both sides, `compare_data` is given the entry indices of both sides and returns
+    /// whether their data differs, if the data is available
+    fn compare<F>(old: &MstDiffSide, new: &MstDiffSide, mut compare_data: F) -> Result<Self>
+    where
+        F: FnMut(usize, usize) -> Result<Option<bool>>,
+    {
+        let header = old
+            .header_fields
+            .iter()
+            .zip(&new.header_fields)
+            .filter(|((_, old_value), (_, new_value))| old_value != new_value)
+            .map(|((field, old_value), (_, new_value))| MstHeaderChange {
+                field: field.clone(),
+                old: *old_value,
+                new: *new_value,
+            })
+            .collect();
+
+        // Entries are matched by name ignoring case, like the engine looks them up
+        let old_indices = entry_indices(&old.entries);
+        let new_indices = entry_indices(&new.entries);
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+        for (new_index, new_entry) in new.entries.iter().enumerate() {
+            let key = new_entry.filename().to_ascii_uppercase();
+            if new_indices[&key] != new_index {
+                continue;
+            }
+
+            let old_index = match old_indices.get(&key) {
+                Some(old_index) => *old_index,
+                None => {
+                    added.push(new_entry.clone());
+                    continue;
+                }
+            };
+            let old_entry = &old.entries[old_index];
+
+            let resized = old_entry.size != new_entry.size;
+            let retimestamped = old_entry.timestamp != new_entry.timestamp;
+            let content = if resized {
+                MstContentChange::Changed
+            } else {
+                match compare_data(old_index, new_index)? {
+                    Some(true) => MstContentChange::Changed,
+                    Some(false) => MstContentChange::Unchanged,
+                    None if old.has_crcs && new.has_crcs => match old_entry.crc != new_entry.crc {
+                        true => MstContentChange::Changed,
+                        false => MstContentChange::Unchanged,
+                    },
+                    None => MstContentChange::Unknown,
+                }
+            };
+
+            if resized || retimestamped || content == MstContentChange::Changed {
+                changed.push(MstEntryChange {
+                    old: old_entry.clone(),
+                    new: new_entry.clone(),
+                    resized,
+                    retimestamped,
+                    content,
+                });
+            }
+        }
+
+        let removed = old
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(old_index, old_entry)| {
+                let key = old_entry.filename().to_ascii_uppercase();
+                old_indices[&key] == *old_index && !new_indices.contains_key(&key)
+            })
+            .map(|(_, old_entry)| old_entry.clone())
+            .collect();
+
+        Ok(Self {
+            header,
+            added,
+            removed,
+            changed,
+        })
+    }
+}
+
+/// Every header field as a name and raw value, in the order they appear in the file
+fn header_fields(mst: &Mst) -> Vec<(String, u32)> {
+    let header = &mst.body.header;
+    let compilers = &header.compilers;
+
+    let mut fields = vec![
+        (
+            "version",
+            u32::from_le_bytes(mst.body.version().into_bytes()),
+        ),
+        ("bytes_in_file", header.bytes_in_file),
+        ("num_entries", header.num_entries),
+        ("num_free_entries", header.num_free_entries),
+        ("num_support_entries", header.num_support_entries),
+        ("num_free_support_entries", header.num_free_support_entries),
+        ("data_offset", header.data_offset),
+        ("tga_compiler_version", compilers.tga_compiler_version),
+        ("ape_compiler_version", compilers.ape_compiler_version),
+        ("mtx_compiler_version", compilers.mtx_compiler_version),
+        ("csv_compiler_version", compilers.csv_compiler_version),
+        ("fnt_compiler_version", compilers.fnt_compiler_version),
+        ("sma_compiler_version", compilers.sma_compiler_version),
+        ("gt_compiler_version", compilers.gt_compiler_version),
+        ("wvb_compiler_version", compilers.wvb_compiler_version),
+        ("fpr_compiler_version", compilers.fpr_compiler_version),
+        ("cam_compiler_version", compilers.cam_compiler_version),
+    ]
+    .into_iter()
+    .map(|(field, value)| (field.to_string(), value))
+    .collect::<Vec<_>>();
+
+    fields.extend(
+        header
+            .reserved
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (format!("reserved[{}]", index), *value)),
+    );
+
+    fields
+}
+
+/// Read the data of an entry, if all of it is present in the file
+fn read_entry_data<R: Read + Seek>(
+    archive: &mut MstArchive<R>,
+    index: usize,
+) -> Result<Option<Vec<u8>>> {
+    // Entries past the end of the archive have no data to compare
+    let mut entry_reader = match archive.open_entry_at(index) {
+        Ok(entry_reader) => entry_reader,
+        Err(_) => return Ok(None),
+    };
+
+    let mut data = Vec::with_capacity(entry_reader.len() as usize);
+    entry_reader.read_to_end(&mut data)?;
+
+    // Stripped archives end before the data does
+    if (data.len() as u64) < entry_reader.len() {
+        return Ok(None);
+    }
+
+    Ok(Some(data))
+}
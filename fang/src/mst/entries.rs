@@ -150,6 +150,32 @@ pub enum Entries {
 }
 
 impl Entries {
+    /// Collect all the entry tables in their canonical form
+    pub fn to_canonical(&self) -> CanonicalInnerEntries {
+        match self {
+            Entries::V180PS2 { inner } => inner.clone(),
+            Entries::V180 { inner } => inner.into_canonical(),
+            Entries::V170 { inner } => inner.into_canonical(),
+            Entries::V160 { inner } => inner.into_canonical(),
+        }
+    }
+
+    /// Create the entry tables of a specific version from their canonical form
+    pub fn from_canonical(version: MstVersionKnown, canonical: CanonicalInnerEntries) -> Entries {
+        match version {
+            MstVersionKnown::V180PS2 => Entries::V180PS2 { inner: canonical },
+            MstVersionKnown::V180 => Entries::V180 {
+                inner: InnerEntries::<_, _>::from_canonical(canonical),
+            },
+            MstVersionKnown::V170 => Entries::V170 {
+                inner: InnerEntries::<_, _>::from_canonical(canonical),
+            },
+            MstVersionKnown::V160 => Entries::V160 {
+                inner: InnerEntries::<_, _>::from_canonical(canonical),
+            },
+        }
+    }
+
     pub fn convert(&self, new_version: MstVersionKnown) -> Entries {
         match (self, new_version) {
             (Entries::V180PS2 { inner }, MstVersionKnown::V180PS2) => Entries::V180PS2 {
@@ -1,6 +1,8 @@
 use binrw::{BinRead, BinWrite, WriteOptions};
 use chrono::{DateTime, Utc};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::io::SeekFrom;
 use std::rc::Rc;
@@ -30,18 +32,50 @@ impl<const T: usize> Debug for Filename<T> {
     }
 }
 
-impl<const T: usize> From<&String> for Filename<T> {
-    fn from(other: &String) -> Self {
-        let mut new_buf = [0u8; T];
-        let max_length = (T - 1).min(other.len());
+impl<const T: usize> Default for Filename<T> {
+    fn default() -> Self {
+        Filename([0u8; T])
+    }
+}
 
-        let other_trimmed = other.split_at(max_length).0.as_bytes();
+impl<const T: usize> Serialize for Filename<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
 
-        new_buf
-            .split_at_mut(max_length)
-            .0
-            .copy_from_slice(other_trimmed);
+impl<'de, const T: usize> Deserialize<'de> for Filename<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let filename = String::deserialize(deserializer)?;
+        if let Some(c) = filename.chars().find(|c| u8::try_from(*c).is_err()) {
+            return Err(D::Error::custom(format!(
+                "{} contains {:?}, which isn't a Latin-1 character",
+                filename, c
+            )));
+        }
+        if filename.chars().count() >= T {
+            return Err(D::Error::custom(format!(
+                "{} does not fit in a {} byte filename",
+                filename, T
+            )));
+        }
+        Ok((&filename).into())
+    }
+}
 
+/// Filenames are stored a byte per character, the same as they're read by `to_string`
+///
+/// Characters past Latin-1 don't fit in a byte and become '?', names that are too long are cut short.
+impl<const T: usize> From<&String> for Filename<T> {
+    fn from(other: &String) -> Self {
+        let mut new_buf = [0u8; T];
+        for (byte, c) in new_buf
+            .iter_mut()
+            .take(T.saturating_sub(1))
+            .zip(other.chars())
+        {
+            *byte = u8::try_from(c).unwrap_or(b'?');
+        }
         Filename(new_buf)
     }
 }
@@ -62,6 +96,15 @@ impl From<Filename<20>> for Filename<16> {
     }
 }
 
+/// Compare filenames the way the entry tables of retail archives are sorted
+///
+/// Names are compared byte by byte after converting them to ASCII uppercase, so `_` sorts after letters.
+pub fn cmp_filenames(a: &str, b: &str) -> Ordering {
+    a.bytes()
+        .map(|c| c.to_ascii_uppercase())
+        .cmp(b.bytes().map(|c| c.to_ascii_uppercase()))
+}
+
 pub trait Entry {
     fn filename(&self) -> String;
     fn offset(&self) -> usize;
@@ -79,7 +122,7 @@ pub type EntryOffsets = Rc<RefCell<Vec<u64>>>;
 pub type CanonicalEntry = EntryV180Variable<20>;
 pub type CanonicalSupportEntry = SupportEntryVariable<20>;
 
-#[derive(BinRead, BinWrite, Debug, Clone)]
+#[derive(BinRead, BinWrite, Serialize, Deserialize, Debug, Clone, Default)]
 #[bw(import(entry_offsets: Option<EntryOffsets>))]
 pub struct EntryV180Variable<const FNL: usize> {
     pub filename: Filename<FNL>,
@@ -136,7 +179,7 @@ impl From<CanonicalEntry> for EntryV180Variable<16> {
     }
 }
 
-#[derive(BinRead, BinWrite, Debug, Clone)]
+#[derive(BinRead, BinWrite, Debug, Clone, Default)]
 #[bw(import(entry_offsets: Option<EntryOffsets>))]
 pub struct EntryV170 {
     pub filename: Filename<16>,
@@ -190,7 +233,7 @@ impl From<CanonicalEntry> for EntryV170 {
     }
 }
 
-#[derive(BinRead, BinWrite, Debug, Clone)]
+#[derive(BinRead, BinWrite, Debug, Clone, Default)]
 #[bw(import(entry_offsets: Option<EntryOffsets>))]
 pub struct EntryV160 {
     pub filename: Filename<16>,
@@ -255,7 +298,7 @@ fn record_entry_offset<W: binrw::io::Write + binrw::io::Seek>(
     value.write_options(writer, opts, ())
 }
 
-#[derive(BinRead, BinWrite, Debug, Clone)]
+#[derive(BinRead, BinWrite, Serialize, Deserialize, Debug, Clone, Default)]
 pub struct SupportEntryVariable<const FNL: usize> {
     pub filename: Filename<FNL>,
     pub timestamp: u32,
@@ -0,0 +1,146 @@
+use glob::{MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+
+use crate::Result;
+
+use super::entry::{CanonicalEntry, CanonicalSupportEntry};
+
+/// Predicate to select entries by name, extension, size and timestamp
+///
+/// Names are matched ignoring case, like the engine looks entries up. An empty filter matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    include_globs: Vec<Pattern>,
+    exclude_globs: Vec<Pattern>,
+    include_regexes: Vec<Regex>,
+    exclude_regexes: Vec<Regex>,
+    extensions: Vec<String>,
+    min_size: Option<u32>,
+    max_size: Option<u32>,
+    modified_after: Option<u32>,
+    modified_before: Option<u32>,
+}
+
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+impl EntryFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match entries whose name matches this or another include pattern
+    pub fn add_include_glob(&mut self, pattern: &str) -> Result<()> {
+        self.include_globs.push(Pattern::new(pattern)?);
+        Ok(())
+    }
+
+    /// Never match entries whose name matches this pattern
+    pub fn add_exclude_glob(&mut self, pattern: &str) -> Result<()> {
+        self.exclude_globs.push(Pattern::new(pattern)?);
+        Ok(())
+    }
+
+    /// Only match entries whose name matches this or another include pattern
+    pub fn add_include_regex(&mut self, pattern: &str) -> Result<()> {
+        self.include_regexes.push(build_regex(pattern)?);
+        Ok(())
+    }
+
+    /// Never match entries whose name matches this pattern
+    pub fn add_exclude_regex(&mut self, pattern: &str) -> Result<()> {
+        self.exclude_regexes.push(build_regex(pattern)?);
+        Ok(())
+    }
+
+    /// Only match entries with this or another added extension, with or without the leading dot
+    pub fn add_extension(&mut self, extension: &str) {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        self.extensions.push(extension);
+    }
+
+    /// Only match entries whose size in bytes is within the inclusive range
+    pub fn set_size_range(&mut self, min_size: Option<u32>, max_size: Option<u32>) {
+        self.min_size = min_size;
+        self.max_size = max_size;
+    }
+
+    /// Only match entries whose timestamp is within the inclusive range, in seconds since the epoch
+    pub fn set_timestamp_range(
+        &mut self,
+        modified_after: Option<u32>,
+        modified_before: Option<u32>,
+    ) {
+        self.modified_after = modified_after;
+        self.modified_before = modified_before;
+    }
+
+    pub fn matches(&self, entry: &CanonicalEntry) -> bool {
+        let size_matches = self
+            .min_size
+            .map_or(true, |min_size| entry.size >= min_size)
+            && self
+                .max_size
+                .map_or(true, |max_size| entry.size <= max_size);
+
+        size_matches
+            && self.matches_timestamp(entry.timestamp)
+            && self.matches_name(&entry.filename.to_string())
+    }
+
+    /// Support entries have no data, so the size range does not apply to them
+    pub fn matches_support(&self, support_entry: &CanonicalSupportEntry) -> bool {
+        self.matches_timestamp(support_entry.timestamp)
+            && self.matches_name(&support_entry.filename.to_string())
+    }
+
+    fn matches_timestamp(&self, timestamp: u32) -> bool {
+        self.modified_after.map_or(true, |after| timestamp >= after)
+            && self
+                .modified_before
+                .map_or(true, |before| timestamp <= before)
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        if !self.extensions.is_empty() {
+            let extension = match name.rsplit_once('.') {
+                Some((_, extension)) => extension.to_ascii_lowercase(),
+                None => String::new(),
+            };
+            if !self.extensions.contains(&extension) {
+                return false;
+            }
+        }
+
+        let has_includes = !self.include_globs.is_empty() || !self.include_regexes.is_empty();
+        let included = self
+            .include_globs
+            .iter()
+            .any(|glob| glob.matches_with(name, GLOB_OPTIONS))
+            || self
+                .include_regexes
+                .iter()
+                .any(|regex| regex.is_match(name));
+        if has_includes && !included {
+            return false;
+        }
+
+        let excluded = self
+            .exclude_globs
+            .iter()
+            .any(|glob| glob.matches_with(name, GLOB_OPTIONS))
+            || self
+                .exclude_regexes
+                .iter()
+                .any(|regex| regex.is_match(name));
+
+        !excluded
+    }
+}
+
+fn build_regex(pattern: &str) -> Result<Regex> {
+    Ok(RegexBuilder::new(pattern).case_insensitive(true).build()?)
+}
@@ -1,6 +1,7 @@
 use binrw::{BinRead, BinWrite};
+use serde::{Deserialize, Serialize};
 
-#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Serialize, Deserialize, Debug, Clone, Copy)]
 pub struct MstHeader {
     pub bytes_in_file: u32,
     pub num_entries: u32,
@@ -15,7 +16,7 @@ pub struct MstHeader {
     pub reserved: [u32; 9],
 }
 
-#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Serialize, Deserialize, Debug, Clone, Copy, Default)]
 pub struct MstCompilers {
     pub tga_compiler_version: u32,
     pub ape_compiler_version: u32,
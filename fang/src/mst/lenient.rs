@@ -0,0 +1,137 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use binrw::{BinReaderExt, Endian};
+
+use crate::{error::TruncationReader, Error, Result};
+
+use super::{
+    entries::Entries, entry::CanonicalEntry, header::MstHeader, Mst, MstBody, MstIdentifier,
+    MstPlatformKnown, MstVersion, MstVersionKnown,
+};
+
+/// How the entry tables of an Mst with an unknown version or platform were read
+#[derive(Debug, Clone, Copy)]
+pub struct MstLayoutGuess {
+    /// Known version whose entry layout was used
+    pub layout: MstVersionKnown,
+    pub platform: MstPlatformKnown,
+    /// Whether the entry tables end in the 2048 bytes before the data offset from the header
+    pub fits_data_offset: bool,
+    /// Entries with a sensible name that point inside the archive
+    pub plausible_entries: usize,
+    pub total_entries: usize,
+}
+
+/// Read an Mst, guessing the layout of its entry tables if the version or platform isn't known
+///
+/// Every known layout is tried, the one with the most plausible entries wins, then the one whose tables
+/// end right before the data. Returns the guess if one had to be made.
+pub fn read_lenient<R: Read + Seek>(reader: &mut R) -> Result<(Mst, Option<MstLayoutGuess>)> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut reader = TruncationReader::new(reader)?;
+    let identifier = reader.read_le::<MstIdentifier>()?;
+    let endian = match identifier.is_little() {
+        true => Endian::Little,
+        false => Endian::Big,
+    };
+    let version = reader.read_type::<MstVersion>(endian)?;
+    let header = reader.read_type::<MstHeader>(endian)?;
+    let table_start = reader.stream_position()?;
+
+    let known_version = MstVersionKnown::try_from(&version).ok();
+    let known_platform = MstPlatformKnown::try_from(&version).ok();
+
+    let mst = |entries| Mst {
+        identifier,
+        body: MstBody {
+            version,
+            header,
+            entries,
+        },
+    };
+
+    if let (Some(known_version), Some(_)) = (known_version, known_platform) {
+        let entries = reader.read_type_args(endian, (known_version, header))?;
+        return Ok((mst(entries), None));
+    }
+
+    // Without a platform only the layout of a known version is worth reading
+    let layouts = match known_version {
+        Some(known_version) => vec![known_version],
+        None => vec![
+            MstVersionKnown::V180,
+            MstVersionKnown::V180PS2,
+            MstVersionKnown::V170,
+            MstVersionKnown::V160,
+        ],
+    };
+
+    let mut best: Option<(MstLayoutGuess, Entries)> = None;
+    for layout in layouts {
+        reader.seek(SeekFrom::Start(table_start))?;
+        let entries: Entries = match reader.read_type_args(endian, (layout, header)) {
+            Ok(entries) => entries,
+            // Tables that run past the end of the file can't be the right layout
+            Err(_) => continue,
+        };
+        let table_end = reader.stream_position()?;
+
+        let data_offset = header.data_offset as u64;
+        let canonical = entries.to_canonical();
+        let guess = MstLayoutGuess {
+            layout,
+            platform: known_platform.unwrap_or_else(|| guess_platform(identifier, layout)),
+            fits_data_offset: table_end <= data_offset && data_offset - table_end < 2048,
+            plausible_entries: canonical
+                .entries
+                .iter()
+                .filter(|e| is_plausible(e, layout, table_end, header.bytes_in_file as u64))
+                .count(),
+            total_entries: canonical.entries.len(),
+        };
+
+        let is_better = match &best {
+            Some((best_guess, _)) => {
+                (guess.plausible_entries, guess.fits_data_offset)
+                    > (best_guess.plausible_entries, best_guess.fits_data_offset)
+            }
+            None => true,
+        };
+        if is_better {
+            best = Some((guess, entries));
+        }
+    }
+
+    match best {
+        Some((guess, entries)) => Ok((mst(entries), Some(guess))),
+        None => Err(Error::UnknownVersion { version }),
+    }
+}
+
+/// GameCube is the only big endian platform, PS2 the only one with long filenames
+fn guess_platform(identifier: MstIdentifier, layout: MstVersionKnown) -> MstPlatformKnown {
+    match (identifier.is_little(), layout) {
+        (false, _) => MstPlatformKnown::GameCube,
+        (true, MstVersionKnown::V180PS2) => MstPlatformKnown::PlayStation2,
+        (true, _) => MstPlatformKnown::Xbox,
+    }
+}
+
+/// Whether an entry read with the given layout looks like a real one
+fn is_plausible(
+    entry: &CanonicalEntry,
+    layout: MstVersionKnown,
+    table_end: u64,
+    bytes_in_file: u64,
+) -> bool {
+    let name = entry.filename.to_string();
+    let name_is_plausible = !name.is_empty()
+        && name.len() <= layout.max_filename_len()
+        && name.bytes().all(|b| b.is_ascii_graphic() || b == b' ');
+
+    let offset = entry.offset as u64;
+    let end = offset + entry.size as u64;
+    let data_is_plausible = entry.size == 0 || (offset >= table_end && end <= bytes_in_file);
+
+    name_is_plausible && data_is_plausible
+}
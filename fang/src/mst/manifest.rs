@@ -0,0 +1,167 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{BufReader, Seek, SeekFrom, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use binrw::BinWriterExt;
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+use super::{
+    entries::{CanonicalInnerEntries, Entries},
+    entry::{CanonicalEntry, CanonicalSupportEntry, Entry},
+    header::MstHeader,
+    Mst, MstBody, MstIdentifier, MstVersion, MstVersionKnown,
+};
+
+/// Everything in an Mst besides the entries' data, so an unpacked archive can be packed back up unchanged
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MstManifest {
+    pub identifier: MstIdentifier,
+    /// Raw version bits, including the platform and tools flags
+    pub version: u32,
+    pub header: MstHeader,
+    pub entries: Vec<MstManifestEntry>,
+    pub free_entries: Vec<CanonicalEntry>,
+    pub support_entries: Vec<CanonicalSupportEntry>,
+    pub free_support_entries: Vec<CanonicalSupportEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MstManifestEntry {
+    /// Path to the entry's data, relative to the manifest
+    pub path: String,
+    #[serde(flatten)]
+    pub entry: CanonicalEntry,
+}
+
+impl MstManifest {
+    pub fn from_mst(mst: &Mst) -> Self {
+        let inner = mst.body.entries.to_canonical();
+
+        Self {
+            identifier: mst.identifier,
+            version: u32::from_le_bytes(mst.body.version().into_bytes()),
+            header: mst.body.header,
+            entries: inner
+                .entries
+                .into_iter()
+                .map(|entry| MstManifestEntry {
+                    path: entry.filename(),
+                    entry,
+                })
+                .collect(),
+            free_entries: inner.free_entries,
+            support_entries: inner.support_entries,
+            free_support_entries: inner.free_support_entries,
+        }
+    }
+
+    pub fn version(&self) -> MstVersion {
+        MstVersion::from_bytes(self.version.to_le_bytes())
+    }
+
+    /// Write the archive described by the manifest, reading each entry's data from `data_dir`
+    ///
+    /// Entries stay at their recorded offsets as long as their data still fits before the next entry,
+    /// anything that grew is appended to the end of the archive. Bytes between entries are written as zeroes.
+    /// Checksums are kept as recorded, the engine's checksum algorithm isn't confirmed yet.
+    pub fn write<W: Write + Seek>(&self, data_dir: &Path, writer: &mut W) -> Result<()> {
+        let version = self.version();
+        let known_version = MstVersionKnown::try_from(&version)?;
+
+        // Every offset in use, to find out how much room each entry has before the next one starts
+        let mut used_offsets = self
+            .entries
+            .iter()
+            .map(|e| e.entry.offset as u64)
+            .collect::<Vec<_>>();
+        used_offsets.sort_unstable();
+        used_offsets.dedup();
+
+        let original_size = self.header.bytes_in_file as u64;
+        let mut append_offset = self
+            .entries
+            .iter()
+            .map(|e| e.entry.offset as u64 + e.entry.size as u64)
+            .max()
+            .unwrap_or(0)
+            .max(original_size);
+
+        // Lay out the entries, keeping the recorded offsets where possible
+        let mut entries = Vec::with_capacity(self.entries.len());
+        let mut data_paths = Vec::with_capacity(self.entries.len());
+        for manifest_entry in &self.entries {
+            let data_path = data_dir.join(&manifest_entry.path);
+            let size = data_path.metadata()?.len();
+
+            let mut entry = manifest_entry.entry.clone();
+            let offset = entry.offset as u64;
+            let next_offset = used_offsets
+                .get(used_offsets.partition_point(|o| *o <= offset))
+                .copied()
+                .unwrap_or(original_size);
+            let slot_end = next_offset.max(offset + entry.size as u64);
+
+            if offset + size > slot_end {
+                if append_offset % 2048 > 0 {
+                    append_offset += 2048 - append_offset % 2048;
+                }
+                entry.offset = append_offset as u32;
+                append_offset += size;
+            }
+            entry.size = size as u32;
+
+            entries.push(entry);
+            data_paths.push(data_path);
+        }
+
+        let mut header = self.header;
+        header.bytes_in_file = append_offset as u32;
+        header.num_entries = entries.len() as u32;
+        header.num_free_entries = self.free_entries.len() as u32;
+        header.num_support_entries = self.support_entries.len() as u32;
+        header.num_free_support_entries = self.free_support_entries.len() as u32;
+
+        let mst = Mst {
+            identifier: self.identifier,
+            body: MstBody {
+                version,
+                header,
+                entries: Entries::from_canonical(
+                    known_version,
+                    CanonicalInnerEntries {
+                        entries: entries.clone(),
+                        free_entries: self.free_entries.clone(),
+                        support_entries: self.support_entries.clone(),
+                        free_support_entries: self.free_support_entries.clone(),
+                    },
+                ),
+            },
+        };
+
+        // Write the Mst header with entries, the offsets are already final
+        writer.write_le_args(&mst, (Rc::new(RefCell::new(Vec::new())),))?;
+        let mut written_end = writer.stream_position()?;
+
+        // Write each entry's data at its offset
+        for (entry, data_path) in entries.iter().zip(data_paths) {
+            writer.seek(SeekFrom::Start(entry.offset as u64))?;
+            let mut data_file = BufReader::new(File::open(&data_path)?);
+            std::io::copy(&mut data_file, writer)?;
+            written_end = written_end.max(entry.offset as u64 + entry.size as u64);
+        }
+
+        // Pad the file up to the size recorded in the header
+        if written_end < append_offset {
+            writer.seek(SeekFrom::Start(append_offset - 1))?;
+            writer.write_all(&[0u8])?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::HashMap, fs::File, io::Cursor, path::Path};
+
+use binrw::BinReaderExt;
+use memmap2::Mmap;
+
+use crate::{error::TruncationReader, Error, Result};
+
+use super::{
+    archive::entry_indices,
+    entry::{CanonicalEntry, CanonicalSupportEntry, Entry},
+    Mst,
+};
+
+/// A memory mapped Mst, handing out the data of its entries without copying
+pub struct MstMmap {
+    mmap: Mmap,
+    mst: Mst,
+    entries: Vec<CanonicalEntry>,
+    support_entries: Vec<CanonicalSupportEntry>,
+    entry_indices: HashMap<String, usize>,
+}
+
+impl MstMmap {
+    /// Map the Mst at the given path
+    ///
+    /// The file must not be modified by anyone else while it is mapped
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        Self::from_mmap(mmap)
+    }
+
+    /// Parse the Mst from an existing mapping
+    pub fn from_mmap(mmap: Mmap) -> Result<Self> {
+        let mst = TruncationReader::new(Cursor::new(&mmap[..]))?.read_le::<Mst>()?;
+        let entries = mst.collect_entries();
+        let support_entries = mst.collect_support_entries();
+        let entry_indices = entry_indices(&entries);
+
+        Ok(Self {
+            mmap,
+            mst,
+            entries,
+            support_entries,
+            entry_indices,
+        })
+    }
+
+    pub fn mst(&self) -> &Mst {
+        &self.mst
+    }
+
+    pub fn entries(&self) -> std::slice::Iter<'_, CanonicalEntry> {
+        self.entries.iter()
+    }
+
+    pub fn support_entries(&self) -> std::slice::Iter<'_, CanonicalSupportEntry> {
+        self.support_entries.iter()
+    }
+
+    /// Look up an entry by name, ignoring case like the engine does
+    pub fn entry(&self, name: &str) -> Option<&CanonicalEntry> {
+        self.entry_indices
+            .get(&name.to_ascii_uppercase())
+            .map(|index| &self.entries[*index])
+    }
+
+    /// Data of an entry by name
+    pub fn data(&self, name: &str) -> Result<&[u8]> {
+        match self.entry(name) {
+            Some(entry) => self.entry_data(entry),
+            None => Err(Error::EntryNotFound {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Data of an entry, after checking that it lies within the archive
+    pub fn entry_data(&self, entry: &CanonicalEntry) -> Result<&[u8]> {
+        let start = entry.offset();
+        let end = start + entry.size();
+
+        // The mapping can be shorter than the header claims, like for stripped archives
+        let len = (self.mst.body.header.bytes_in_file as usize).min(self.mmap.len());
+        if end > len {
+            return Err(Error::EntryOutOfBounds {
+                name: entry.filename(),
+                end: end as u64,
+                len: len as u64,
+            });
+        }
+
+        Ok(&self.mmap[start..end])
+    }
+}
@@ -1,7 +1,12 @@
 #![allow(dead_code)]
 
+use std::str::FromStr;
+
 use binrw::{BinRead, BinWrite};
 use modular_bitfield::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
 
 pub mod entry;
 use entry::{CanonicalEntry, CanonicalSupportEntry, EntryOffsets};
@@ -12,8 +17,25 @@ use entries::Entries;
 pub mod header;
 use header::MstHeader;
 
+pub mod archive;
+
 pub mod builder;
 
+pub mod crc;
+
+pub mod diff;
+
+pub mod filter;
+
+pub mod lenient;
+
+pub mod manifest;
+
+pub mod patch;
+
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
 #[derive(BinRead, BinWrite, Debug)]
 #[bw(import(entry_offsets: EntryOffsets))]
 pub struct Mst {
@@ -58,8 +80,8 @@ impl Mst {
 #[derive(BinRead, BinWrite, Debug, Clone)]
 #[bw(import(entry_offsets: EntryOffsets))]
 pub struct MstBody {
-    #[br(assert(MstVersionKnown::try_from(&version).is_ok(), "not a known mst version: {:?}", version))]
-    #[bw(assert(MstVersionKnown::try_from(version).is_ok(), "not a known mst version: {:?}", version))]
+    #[br(assert(MstVersionKnown::try_from(&version).is_ok(), Error::UnknownVersion { version }))]
+    #[bw(assert(MstVersionKnown::try_from(version).is_ok(), Error::UnknownVersion { version: *version }))]
     version: MstVersion,
 
     pub header: MstHeader,
@@ -96,7 +118,7 @@ impl MstBody {
     }
 }
 
-#[derive(BinRead, BinWrite, Debug, Clone, Copy)]
+#[derive(BinRead, BinWrite, Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum MstIdentifier {
     #[brw(magic(b"FANG"))]
     FangLittleEndian,
@@ -180,6 +202,26 @@ impl MstVersion {
     }
 }
 
+impl Serialize for MstVersion {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("MstVersion", 8)?;
+        state.serialize_field("major", &self.major())?;
+        state.serialize_field("minor", &self.minor())?;
+        state.serialize_field("patch", &self.patch())?;
+        state.serialize_field("xbox", &(self.xbox() == 1))?;
+        state.serialize_field("pc", &(self.pc() == 1))?;
+        state.serialize_field("tools", &(self.tools() == 1))?;
+        state.serialize_field("gc", &(self.gc() == 1))?;
+        state.serialize_field("ps2", &(self.ps2() == 1))?;
+        state.end()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MstVersionKnown {
     V180PS2,
@@ -188,10 +230,36 @@ pub enum MstVersionKnown {
     V160,
 }
 
+impl MstVersionKnown {
+    /// Pick the known version for a 1.x minor version on the given platform
+    pub fn from_minor(minor: u8, platform: MstPlatformKnown) -> Result<Self> {
+        match (minor, platform) {
+            (8, MstPlatformKnown::PlayStation2) => Ok(MstVersionKnown::V180PS2),
+            (8, _) => Ok(MstVersionKnown::V180),
+            (7, _) => Ok(MstVersionKnown::V170),
+            (6, _) => Ok(MstVersionKnown::V160),
+            _ => {
+                let mut version = MstVersion::new();
+                version.set_major(1);
+                version.set_minor(minor);
+                Err(Error::UnknownVersion { version })
+            }
+        }
+    }
+
+    /// Longest filename in bytes that fits an entry of this version, leaving room for the null terminator
+    pub fn max_filename_len(&self) -> usize {
+        match self {
+            MstVersionKnown::V180PS2 => 19,
+            MstVersionKnown::V180 | MstVersionKnown::V170 | MstVersionKnown::V160 => 15,
+        }
+    }
+}
+
 impl TryFrom<&MstVersion> for MstVersionKnown {
-    type Error = anyhow::Error;
+    type Error = Error;
 
-    fn try_from(mst_version: &MstVersion) -> anyhow::Result<Self> {
+    fn try_from(mst_version: &MstVersion) -> Result<Self> {
         match (
             mst_version.major(),
             mst_version.minor(),
@@ -202,12 +270,9 @@ impl TryFrom<&MstVersion> for MstVersionKnown {
             (1, 8, 0, false) => Ok(MstVersionKnown::V180),
             (1, 7, 0, _) => Ok(MstVersionKnown::V170),
             (1, 6, 0, _) => Ok(MstVersionKnown::V160),
-            _ => anyhow::bail!(
-                "{}.{}.{} is not a known Mst version",
-                mst_version.major(),
-                mst_version.minor(),
-                mst_version.patch()
-            ),
+            _ => Err(Error::UnknownVersion {
+                version: *mst_version,
+            }),
         }
     }
 }
@@ -221,9 +286,9 @@ pub enum MstPlatformKnown {
 }
 
 impl TryFrom<&MstVersion> for MstPlatformKnown {
-    type Error = anyhow::Error;
+    type Error = Error;
 
-    fn try_from(mst_version: &MstVersion) -> anyhow::Result<Self> {
+    fn try_from(mst_version: &MstVersion) -> Result<Self> {
         if mst_version.xbox() > 0 {
             return Ok(MstPlatformKnown::Xbox);
         }
@@ -236,6 +301,26 @@ impl TryFrom<&MstVersion> for MstPlatformKnown {
         if mst_version.ps2() > 0 {
             return Ok(MstPlatformKnown::PlayStation2);
         }
-        anyhow::bail!("{:?} does not represent a known Mst platform", mst_version);
+        Err(Error::UnknownPlatform {
+            version: *mst_version,
+        })
+    }
+}
+
+impl FromStr for MstPlatformKnown {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "xbox" | "xb" => Ok(MstPlatformKnown::Xbox),
+            "pc" => Ok(MstPlatformKnown::PC),
+            "gamecube" | "gc" => Ok(MstPlatformKnown::GameCube),
+            "playstation2" | "ps2" => Ok(MstPlatformKnown::PlayStation2),
+            _ => Err(Error::UnknownValue {
+                kind: "Mst platform",
+                value: s.to_string(),
+                expected: "xbox, pc, gc, ps2",
+            }),
+        }
     }
 }
@@ -0,0 +1,269 @@
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    io::{Cursor, Read, Seek, SeekFrom, Write},
+    rc::Rc,
+};
+
+use binrw::{BinReaderExt, BinWriterExt};
+use chrono::Utc;
+
+use crate::{error::TruncationReader, Error, Result};
+
+use super::{
+    crc::entry_crc,
+    entries::{CanonicalInnerEntries, Entries},
+    entry::{cmp_filenames, CanonicalEntry, Entry},
+    header::MstHeader,
+    Mst, MstBody, MstIdentifier, MstVersion, MstVersionKnown,
+};
+
+/// Modifies an existing Mst in place, without rebuilding it
+///
+/// Entry data is overwritten where it was if the new data fits before the data of the next entry, and
+/// appended to the end of the archive otherwise. Data shared by several entries is never overwritten.
+/// Appended data keeps the alignment the archive already uses, 2048 bytes on most platforms but less on
+/// PlayStation 2. New entries take up free entry slots, so the tables never move. The updated header and
+/// tables are only written by [`MstPatcher::finish`].
+pub struct MstPatcher<F: Read + Write + Seek> {
+    file: F,
+    identifier: MstIdentifier,
+    version: MstVersion,
+    known_version: MstVersionKnown,
+    header: MstHeader,
+    entries: CanonicalInnerEntries,
+    alignment: u64,
+    write_crcs: bool,
+}
+
+impl<F: Read + Write + Seek> MstPatcher<F> {
+    pub fn new(mut file: F) -> Result<Self> {
+        file.seek(SeekFrom::Start(0))?;
+        let mst = TruncationReader::new(&mut file)?.read_le::<Mst>()?;
+        let known_version = MstVersionKnown::try_from(mst.body.version())?;
+        let entries = mst.body.entries.to_canonical();
+        let alignment = data_alignment(&mst.body.header, &entries);
+
+        Ok(Self {
+            identifier: mst.identifier,
+            version: *mst.body.version(),
+            known_version,
+            header: mst.body.header,
+            entries,
+            alignment,
+            write_crcs: false,
+            file,
+        })
+    }
+
+    /// Index of an entry by name, ignoring case like the engine does
+    fn entry_index(&self, name: &str) -> Option<usize> {
+        self.entries
+            .entries
+            .iter()
+            .position(|e| cmp_filenames(&e.filename(), name) == Ordering::Equal)
+    }
+
+    /// Compute the checksum of replaced and added data
+    ///
+    /// Off by default, since [`entry_crc`] hasn't been confirmed to match the engine's checksum. Replaced
+    /// entries keep their old checksum and added ones get 0.
+    pub fn set_write_crcs(&mut self, write_crcs: bool) {
+        self.write_crcs = write_crcs;
+    }
+
+    /// Check if an entry by the specified name exists
+    pub fn has_entry(&self, name: &str) -> bool {
+        self.entry_index(name).is_some()
+    }
+
+    /// Number of entries that can still be added
+    pub fn free_entry_count(&self) -> usize {
+        self.entries.free_entries.len()
+    }
+
+    /// Replace the data of an existing entry
+    pub fn replace_entry(&mut self, name: &str, data: &[u8], timestamp: Option<u32>) -> Result<()> {
+        let index = match self.entry_index(name) {
+            Some(index) => index,
+            None => {
+                return Err(Error::EntryNotFound {
+                    name: name.to_string(),
+                })
+            }
+        };
+
+        // Overwrite the old data if the new data fits and no other entry uses it, otherwise append it
+        let offset = self.entries.entries[index].offset as u64;
+        let fits = offset + data.len() as u64 <= self.slot_end(index);
+        let offset = match fits && !self.is_shared(index) {
+            true => offset,
+            false => self.append_offset(),
+        };
+        self.write_data(offset, data)?;
+
+        let entry = &mut self.entries.entries[index];
+        entry.offset = offset as u32;
+        entry.size = data.len() as u32;
+        entry.timestamp = timestamp.unwrap_or_else(|| Utc::now().timestamp() as u32);
+        if self.write_crcs {
+            entry.crc = entry_crc(data);
+        }
+
+        Ok(())
+    }
+
+    /// Add a new entry in one of the free entry slots, appending its data
+    pub fn add_entry(&mut self, name: &str, data: &[u8], timestamp: Option<u32>) -> Result<()> {
+        if self.has_entry(name) {
+            return Err(Error::DuplicateEntry {
+                name: name.to_string(),
+            });
+        }
+        if name.len() > self.known_version.max_filename_len() {
+            return Err(Error::FilenameTooLong {
+                name: name.to_string(),
+                version: self.known_version,
+            });
+        }
+        if self.entries.free_entries.pop().is_none() {
+            return Err(Error::NoFreeEntrySlots {
+                name: name.to_string(),
+            });
+        }
+
+        let offset = self.append_offset();
+        self.write_data(offset, data)?;
+
+        let entry = CanonicalEntry {
+            filename: (&name.to_string()).into(),
+            flags: 0,
+            offset: offset as u32,
+            size: data.len() as u32,
+            timestamp: timestamp.unwrap_or_else(|| Utc::now().timestamp() as u32),
+            crc: match self.write_crcs {
+                true => entry_crc(data),
+                false => 0,
+            },
+        };
+
+        // Keep the entry table sorted the way the engine expects it
+        let position = self
+            .entries
+            .entries
+            .partition_point(|e| cmp_filenames(&e.filename(), name) == Ordering::Less);
+        self.entries.entries.insert(position, entry);
+
+        Ok(())
+    }
+
+    /// Remove an entry, returning its slot to the free entries
+    ///
+    /// The entry's data is left in place as unused space
+    pub fn delete_entry(&mut self, name: &str) -> Result<()> {
+        let index = match self.entry_index(name) {
+            Some(index) => index,
+            None => {
+                return Err(Error::EntryNotFound {
+                    name: name.to_string(),
+                })
+            }
+        };
+
+        self.entries.entries.remove(index);
+        self.entries.free_entries.push(CanonicalEntry::default());
+
+        Ok(())
+    }
+
+    /// Write the updated header and entry tables, returning the underlying file
+    pub fn finish(mut self) -> Result<F> {
+        self.header.num_entries = self.entries.entries.len() as u32;
+        self.header.num_free_entries = self.entries.free_entries.len() as u32;
+        if let Some(lowest_offset) = self.entries.entries.iter().map(|e| e.offset).min() {
+            self.header.data_offset = self.header.data_offset.min(lowest_offset);
+        }
+
+        let mst = Mst {
+            identifier: self.identifier,
+            body: MstBody {
+                version: self.version,
+                header: self.header,
+                entries: Entries::from_canonical(self.known_version, self.entries),
+            },
+        };
+
+        // The tables keep their size, so they can be written over the old ones in one go
+        let mut table_buf = Cursor::new(Vec::new());
+        table_buf.write_le_args(&mst, (Rc::new(RefCell::new(Vec::new())),))?;
+
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(table_buf.get_ref())?;
+        self.file.flush()?;
+
+        Ok(self.file)
+    }
+
+    /// End of the space available to an entry's data without running into the next entry's data
+    ///
+    /// The last entry can grow past the end of the archive.
+    fn slot_end(&self, index: usize) -> u64 {
+        let offset = self.entries.entries[index].offset as u64;
+
+        self.entries
+            .entries
+            .iter()
+            .map(|e| e.offset as u64)
+            .filter(|o| *o > offset)
+            .min()
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Check if the data of an entry starts at the same offset as another entry's
+    fn is_shared(&self, index: usize) -> bool {
+        let offset = self.entries.entries[index].offset;
+
+        self.entries
+            .entries
+            .iter()
+            .enumerate()
+            .any(|(i, e)| i != index && e.offset == offset)
+    }
+
+    /// Aligned offset at the end of the archive, where new data can be appended
+    fn append_offset(&self) -> u64 {
+        align(self.header.bytes_in_file as u64, self.alignment)
+    }
+
+    fn write_data(&mut self, offset: u64, data: &[u8]) -> Result<()> {
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(data)?;
+
+        let end = offset + data.len() as u64;
+        self.header.bytes_in_file = self.header.bytes_in_file.max(end as u32);
+
+        Ok(())
+    }
+}
+
+/// Largest power of two up to 2048 that the data offset and all entry offsets are a multiple of
+fn data_alignment(header: &MstHeader, entries: &CanonicalInnerEntries) -> u64 {
+    let offsets = entries.entries.iter().map(|e| e.offset as u64);
+
+    std::iter::once(header.data_offset as u64)
+        .chain(offsets)
+        .fold(2048, |mut alignment, offset| {
+            while offset % alignment != 0 {
+                alignment /= 2;
+            }
+            alignment
+        })
+}
+
+/// Round up to a multiple of the alignment
+fn align(offset: u64, alignment: u64) -> u64 {
+    match offset % alignment {
+        0 => offset,
+        rem => offset + alignment - rem,
+    }
+}
@@ -1,5 +1,6 @@
-use binrw::{BinRead, PosValue};
-use std::io::SeekFrom;
+use crate::error::TruncationReader;
+use binrw::{BinRead, BinReaderExt, PosValue};
+use std::io::{Read, Seek, SeekFrom};
 
 #[derive(BinRead, Debug)]
 pub struct SndInitRdg {
@@ -20,6 +21,13 @@ pub struct SndInitRdg {
     pub sdir_file: SdirFile,
 }
 
+impl SndInitRdg {
+    /// Read a snd_init.rdg, which is always big endian
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> crate::Result<Self> {
+        Ok(TruncationReader::new(reader)?.read_be()?)
+    }
+}
+
 #[derive(BinRead, Debug)]
 #[br(import(size_bytes: u32))]
 pub struct SdirFile {
@@ -1,4 +1,6 @@
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+use crate::{Error, Result};
 
 pub fn vec_to_null_terminated_str(buf: Vec<u8>) -> String {
     String::from_utf8_lossy(&buf)
@@ -23,3 +25,20 @@ pub fn epoch_to_chrono(epoch: u32) -> DateTime<Utc> {
 pub fn chrono_to_epoch(datetime: &DateTime<Utc>) -> u32 {
     datetime.timestamp() as u32
 }
+
+/// Parse a timestamp given as seconds since the epoch, an RFC 3339 date and time, or a date
+pub fn parse_epoch(s: &str) -> Result<u32> {
+    if let Ok(epoch) = s.parse::<u32>() {
+        return Ok(epoch);
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Ok(chrono_to_epoch(&datetime.with_timezone(&Utc)));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_hms(0, 0, 0).timestamp() as u32);
+    }
+
+    Err(Error::InvalidTimestamp {
+        value: s.to_string(),
+    })
+}
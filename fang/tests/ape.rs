@@ -0,0 +1,594 @@
+use std::io::Cursor;
+
+use fang::ape::{
+    flags::{ApeFlags, BoneFlags, LightFlags, LightKind},
+    geometry::{CFMtx43A, CFVec3},
+    gltf::GltfDocument,
+    patch::{ApeBonePatch, ApeItemKey, ApePatch},
+    skeleton::SkeletonIssue,
+    Ape,
+};
+
+/// Writes values in the byte order of the ape being built
+struct ApeWriter {
+    data: Vec<u8>,
+    is_little: bool,
+}
+
+impl ApeWriter {
+    fn u32(&mut self, value: u32) {
+        match self.is_little {
+            true => self.data.extend_from_slice(&value.to_le_bytes()),
+            false => self.data.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.u32(value.to_bits());
+    }
+
+    fn name(&mut self, name: &str, len: usize) {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.resize(len, 0);
+        self.data.extend_from_slice(&bytes);
+    }
+}
+
+/// Build an ape without segments, lights or materials, with bones given as a name, parent index and offset
+///
+/// Bones are only translated, the offset is from their parent and adds up along the parents to the offset
+/// from the model. Parents have to come before their children.
+fn build_ape(is_little: bool, bones: &[(&str, u8, [f32; 3])]) -> Vec<u8> {
+    let mut writer = ApeWriter {
+        data: Vec::new(),
+        is_little,
+    };
+
+    writer.name("test_model", 16);
+    // Bounding sphere radius and center, then the bounding box
+    for value in [2.5, 0.0, 1.0, 0.0, -1.0, -1.0, -1.0, 1.0, 3.0, 1.0] {
+        writer.f32(value);
+    }
+    // Flags and collision mask
+    writer.data.extend_from_slice(&[0u8; 4]);
+    // Counts, only the bone count is used
+    writer.data.extend_from_slice(&[0, 0, bones.len() as u8]);
+    writer.data.extend_from_slice(&[0u8; 9]);
+    for distance in [10.0, 20.0, 40.0, 80.0, 0.0, 0.0, 0.0, 0.0] {
+        writer.f32(distance);
+    }
+    // Pointers, the bones and then the skeleton indices follow the header
+    let skeleton_offset = 136 + bones.len() as u32 * 320;
+    writer.u32(0);
+    writer.u32(if bones.is_empty() { 0 } else { 136 });
+    writer.u32(0);
+    writer.u32(if bones.is_empty() { 0 } else { skeleton_offset });
+    for _ in 0..4 {
+        writer.u32(0);
+    }
+
+    let children = (0..bones.len())
+        .map(|index| {
+            (0..bones.len() as u8)
+                .filter(|child| bones[*child as usize].1 as usize == index)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut model_offsets: Vec<[f32; 3]> = Vec::with_capacity(bones.len());
+    for (_, parent_index, [x, y, z]) in bones {
+        let [px, py, pz] = model_offsets
+            .get(*parent_index as usize)
+            .copied()
+            .unwrap_or_default();
+        model_offsets.push([px + x, py + y, pz + z]);
+    }
+
+    let mut child_array_start_index = 0;
+    for (((name, parent_index, offset), model_offset), children) in
+        bones.iter().zip(&model_offsets).zip(&children)
+    {
+        writer.name(name, 32);
+        // Rest matrices, the model to bone and parent to bone ones move the other way
+        for (sign, [x, y, z]) in [
+            (1.0, model_offset),
+            (-1.0, model_offset),
+            (-1.0, offset),
+            (1.0, offset),
+        ] {
+            for row in [
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [sign * x, sign * y, sign * z],
+            ] {
+                for value in row {
+                    writer.f32(value);
+                }
+                writer.f32(0.0);
+            }
+        }
+        // Segmented bounding sphere
+        for _ in 0..4 {
+            writer.f32(0.0);
+        }
+        // Skeleton, flags, part id and padding
+        let child_bone_count = children.len() as u8;
+        writer.data.extend_from_slice(&[
+            *parent_index,
+            child_bone_count,
+            child_array_start_index,
+            0,
+            0,
+        ]);
+        writer.data.extend_from_slice(&[0u8; 11]);
+        child_array_start_index += child_bone_count;
+    }
+
+    writer.data.extend(children.concat());
+
+    writer.data
+}
+
+#[test]
+fn test_read_auto() {
+    for is_little in [true, false] {
+        let data = build_ape(is_little, &[]);
+        assert_eq!(data.len(), 136, "header size");
+
+        let mut reader = Cursor::new(data);
+        assert_eq!(
+            Ape::detect_little_endian(&mut reader).expect("Failed to detect byte order"),
+            is_little,
+            "detected byte order"
+        );
+
+        let ape = Ape::read_auto(&mut reader).expect("Failed to read ape");
+        assert_eq!(ape.name, "test_model", "name");
+        assert_eq!(ape.bound_sphere.radius, 2.5, "bounding sphere radius");
+        assert_eq!(ape.bound_box_max.y, 3.0, "bounding box");
+        assert_eq!(ape.load_distances[3], 80.0, "load distances");
+    }
+}
+
+#[test]
+fn test_gltf_skeleton() {
+    let data = build_ape(
+        false,
+        &[
+            ("root", 255, [0.0, 0.0, 0.0]),
+            ("spine", 0, [0.0, 1.0, 0.0]),
+            ("head", 1, [0.0, 0.5, 0.25]),
+            ("arm", 1, [1.0, 0.0, 0.0]),
+        ],
+    );
+    let ape = Ape::read_auto(&mut Cursor::new(data)).expect("Failed to read ape");
+    assert_eq!(ape.bones.len(), 4, "bone count");
+    assert_eq!(ape.bone_children(1), Some(&[2, 3][..]), "skeleton children");
+    assert_eq!(ape.bone_children(3), Some(&[][..]), "skeleton leaf");
+
+    let document = GltfDocument::from_ape(&ape);
+    assert_eq!(
+        document.nodes.len(),
+        5,
+        "a node for the model and each bone"
+    );
+
+    let node_names = document
+        .nodes
+        .iter()
+        .map(|node| node.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        node_names,
+        ["test_model", "root", "spine", "head", "arm"],
+        "node names"
+    );
+
+    assert_eq!(document.nodes[0].children, [1], "model node children");
+    assert_eq!(document.nodes[1].children, [2], "root bone children");
+    assert_eq!(document.nodes[2].children, [3, 4], "spine bone children");
+    assert!(document.nodes[3].children.is_empty(), "head bone children");
+
+    let head_matrix = document.nodes[3].matrix.expect("Bone without a matrix");
+    assert_eq!(
+        head_matrix[12..],
+        [0.0, 0.5, -0.25, 1.0],
+        "head translation, mirrored along Z"
+    );
+    assert!(document.materials.is_empty(), "materials");
+
+    assert_eq!(document.skins.len(), 1, "skin");
+    assert_eq!(document.skins[0].joints, [1, 2, 3, 4], "skin joints");
+    let accessor = &document.accessors[document.skins[0].inverse_bind_matrices];
+    assert_eq!(
+        (accessor.count, accessor.kind.as_str()),
+        (4, "MAT4"),
+        "inverse bind matrices accessor"
+    );
+    let buffer = &document.buffers[document.buffer_views[accessor.buffer_view].buffer];
+    assert_eq!(buffer.byte_length, 4 * 64, "inverse bind matrices size");
+
+    // The head is at (0, 1.5, 0.25) in model space, its inverse bind matrix moves it back to the origin
+    let head_inverse_bind_matrix = buffer.data[2 * 64..3 * 64]
+        .chunks(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        head_inverse_bind_matrix[12..],
+        [0.0, -1.5, 0.25, 1.0],
+        "head inverse bind translation, mirrored along Z"
+    );
+
+    let data = build_ape(false, &[]);
+    let ape = Ape::read_auto(&mut Cursor::new(data)).expect("Failed to read ape");
+    let document = GltfDocument::from_ape(&ape);
+    assert!(document.skins.is_empty(), "no skin without bones");
+    assert!(document.buffers.is_empty(), "no buffers without bones");
+}
+
+#[test]
+fn test_flags() {
+    let flags = ApeFlags::from_raw(0x8005);
+    assert_eq!(
+        flags.set_flags(),
+        ["unknown_0", "unknown_2", "unknown_15"],
+        "set flags"
+    );
+    assert!(flags.unknown_15() && !flags.unknown_1(), "flag getters");
+    assert_eq!(flags.raw(), 0x8005, "raw flags");
+    assert_eq!(
+        format!("{:?}", flags),
+        "ApeFlags(0x8005, unknown_0 | unknown_2 | unknown_15)",
+        "flags debug output"
+    );
+    assert_eq!(
+        format!("{:?}", ApeFlags::new()),
+        "ApeFlags(0x0000)",
+        "empty flags"
+    );
+    assert_eq!(
+        format!("{:?}", BoneFlags::new().with_unknown_7(true)),
+        "BoneFlags(0x80, unknown_7)",
+        "bone flags debug output"
+    );
+    assert_eq!(
+        format!("{:?}", LightFlags::from_raw(0x10)),
+        "LightFlags(0x00000010, unknown_4)",
+        "light flags debug output"
+    );
+
+    for value in 0..=u8::MAX {
+        assert_eq!(
+            LightKind::from_u8(value).to_u8(),
+            value,
+            "light kind round trip"
+        );
+    }
+    assert_eq!(LightKind::from_u8(2), LightKind::Spot, "light kind");
+    assert_eq!(
+        LightKind::from_u8(9),
+        LightKind::Unknown(9),
+        "unknown light kind"
+    );
+}
+
+#[test]
+fn test_write_in_place() {
+    for is_little in [true, false] {
+        let data = build_ape(
+            is_little,
+            &[
+                ("root", 255, [0.0, 0.0, 0.0]),
+                ("spine", 0, [0.0, 1.0, 0.0]),
+                ("head", 1, [0.0, 0.5, 0.25]),
+            ],
+        );
+        let mut ape = Ape::read_auto(&mut Cursor::new(&data)).expect("Failed to read ape");
+
+        let mut writer = Cursor::new(data.clone());
+        ape.write_in_place(&mut writer, is_little)
+            .expect("Failed to write ape");
+        assert!(
+            writer.get_ref() == &data,
+            "unmodified ape is written back unchanged"
+        );
+
+        ape.load_distances[0] = 15.0;
+        ape.bones[2].at_rest_bone_to_parent_mtx.p.y = 0.75;
+        ape.write_in_place(&mut writer, is_little)
+            .expect("Failed to write modified ape");
+        assert_eq!(writer.get_ref().len(), data.len(), "ape size");
+
+        writer.set_position(0);
+        let modified = Ape::read_endian(&mut writer, is_little).expect("Failed to reread ape");
+        assert_eq!(modified.load_distances[0], 15.0, "modified load distance");
+        assert_eq!(
+            modified.bones[2].at_rest_bone_to_parent_mtx.p.y, 0.75,
+            "modified bone"
+        );
+        assert_eq!(modified.bone_children(0), Some(&[1][..]), "skeleton");
+
+        ape.bones.pop();
+        assert!(
+            ape.write_in_place(&mut writer, is_little).is_err(),
+            "removing a bone can't be written in place"
+        );
+    }
+}
+
+#[test]
+fn test_write_in_place_raw_bytes() {
+    let mut data = build_ape(
+        true,
+        &[
+            ("root", 255, [0.0, 0.0, 0.0]),
+            ("spine", 0, [0.0, 1.0, 0.0]),
+        ],
+    );
+    // Leftovers after the model name's terminator, a bone name that isn't UTF-8 and used bone padding
+    data[11..16].copy_from_slice(b"\xaa\xbb\xcc\xdd\xee");
+    data[136..145].copy_from_slice(b"r\xf6\xf6t\0junk");
+    data[136 + 309..136 + 320].copy_from_slice(&[0x55; 11]);
+    data[456 + 309..456 + 320].copy_from_slice(&[0x66; 11]);
+
+    let mut ape = Ape::read_auto(&mut Cursor::new(&data)).expect("Failed to read ape");
+    assert_eq!(ape.name, "test_model", "name before the leftovers");
+    assert_eq!(ape.bones[0].name.as_bytes(), b"r\xf6\xf6t", "raw bone name");
+    assert_eq!(
+        ape.bones[0].name.to_str(),
+        "r\u{fffd}\u{fffd}t",
+        "bone name as a string"
+    );
+    assert_eq!(ape.bones[1].padding, [0x66; 11], "bone padding");
+
+    let mut writer = Cursor::new(data.clone());
+    ape.write_in_place(&mut writer, true)
+        .expect("Failed to write ape");
+    assert!(
+        writer.get_ref() == &data,
+        "names and padding are written back unchanged"
+    );
+
+    let patch = ApePatch {
+        bones: vec![ApeBonePatch {
+            key: ApeItemKey::Index(0),
+            name: Some("hip".to_string()),
+        }],
+        ..Default::default()
+    };
+    patch.apply(&mut ape).expect("Failed to apply patch");
+    ape.write_in_place(&mut writer, true)
+        .expect("Failed to write renamed ape");
+
+    let mut expected = data.clone();
+    expected[136..140].copy_from_slice(b"hip\0");
+    assert!(
+        writer.get_ref() == &expected,
+        "renaming only changes the name up to its terminator"
+    );
+}
+
+#[test]
+fn test_patch() {
+    let data = build_ape(
+        true,
+        &[
+            ("root", 255, [0.0, 0.0, 0.0]),
+            ("spine", 0, [0.0, 1.0, 0.0]),
+        ],
+    );
+    let mut ape = Ape::read_auto(&mut Cursor::new(data)).expect("Failed to read ape");
+
+    let patch = ApePatch {
+        load_distance_scale: Some(2.0),
+        bones: vec![
+            ApeBonePatch {
+                key: ApeItemKey::Name("SPINE".to_string()),
+                name: Some("torso".to_string()),
+            },
+            ApeBonePatch {
+                key: ApeItemKey::Index(0),
+                name: Some("pelvis".to_string()),
+            },
+        ],
+        ..Default::default()
+    };
+    patch.apply(&mut ape).expect("Failed to apply patch");
+    assert_eq!(
+        ape.load_distances[..4],
+        [20.0, 40.0, 80.0, 160.0],
+        "scaled load distances"
+    );
+    assert_eq!(ape.bones[0].name, "pelvis", "bone renamed by index");
+    assert_eq!(ape.bones[1].name, "torso", "bone renamed by name");
+
+    let invalid_patches = [
+        ApePatch {
+            load_distances: Some([f32::NAN; 8]),
+            ..Default::default()
+        },
+        ApePatch {
+            bones: vec![ApeBonePatch {
+                key: ApeItemKey::Index(2),
+                name: None,
+            }],
+            ..Default::default()
+        },
+        ApePatch {
+            bones: vec![ApeBonePatch {
+                key: ApeItemKey::Name("torso".to_string()),
+                name: Some("a_bone_name_too_long_to_fit_in_32".to_string()),
+            }],
+            ..Default::default()
+        },
+    ];
+    for invalid_patch in invalid_patches {
+        let patch = ApePatch {
+            load_distance_scale: Some(0.5),
+            ..invalid_patch
+        };
+        assert!(patch.apply(&mut ape).is_err(), "invalid patch {:?}", patch);
+        assert_eq!(
+            ape.load_distances[0], 20.0,
+            "ape unchanged by invalid patch"
+        );
+        assert_eq!(
+            ape.bones[1].name, "torso",
+            "bone unchanged by invalid patch"
+        );
+    }
+
+    // Both values are fine on their own, but not once they're multiplied
+    let overflowing_patch = ApePatch {
+        load_distances: Some([f32::MAX; 8]),
+        load_distance_scale: Some(2.0),
+        ..Default::default()
+    };
+    assert!(
+        overflowing_patch.apply(&mut ape).is_err(),
+        "load distances scaled to infinity"
+    );
+    assert_eq!(
+        ape.load_distances[0], 20.0,
+        "ape unchanged by overflowing patch"
+    );
+}
+
+#[test]
+fn test_skeleton() {
+    let data = build_ape(
+        true,
+        &[
+            ("root", 255, [0.0, 0.0, 0.0]),
+            ("spine", 0, [0.0, 1.0, 0.0]),
+            ("head", 1, [0.0, 0.5, 0.25]),
+            ("arm", 1, [1.0, 0.0, 0.0]),
+        ],
+    );
+    let mut ape = Ape::read_auto(&mut Cursor::new(data)).expect("Failed to read ape");
+
+    assert_eq!(
+        ape.bone_tree(),
+        [(0, 0), (1, 1), (2, 2), (3, 2)],
+        "bones in depth first order"
+    );
+    assert!(ape.validate_skeleton().is_empty(), "valid skeleton");
+
+    let bvh = ape.skeleton_to_bvh();
+    let joints = bvh
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("JOINT "))
+        .collect::<Vec<_>>();
+    assert_eq!(joints, ["root", "spine", "head", "arm"], "bvh joints");
+    assert_eq!(
+        bvh.matches('{').count(),
+        bvh.matches('}').count(),
+        "bvh braces balance"
+    );
+    assert!(
+        bvh.contains("\t\t\t\tOFFSET 0.000000 0.500000 0.250000"),
+        "bvh offset of head"
+    );
+    assert_eq!(
+        bvh.lines().last().map(|line| line.split(' ').count()),
+        Some(6 + 3 * 4),
+        "bvh channels in the rest pose frame"
+    );
+
+    // Move the arm below the root without updating the child arrays
+    ape.bones[3].skeleton.parent_bone_index = 0;
+    ape.bones[2].at_rest_model_to_bone_mtx.p.y = 0.0;
+    ape.root_bone_index = 4;
+
+    let issues = ape.validate_skeleton();
+    for expected in [
+        SkeletonIssue::RootOutOfRange {
+            root_bone_index: 4,
+            bone_count: 4,
+        },
+        SkeletonIssue::ChildMismatch { bone: 1, child: 3 },
+        SkeletonIssue::MissingChild { bone: 3, parent: 0 },
+    ] {
+        assert!(issues.contains(&expected), "{:?} in {:?}", expected, issues);
+    }
+    assert!(
+        issues
+            .iter()
+            .any(|issue| matches!(issue, SkeletonIssue::NotInverse { bone: 2, .. })),
+        "rest matrices of the head are no longer inverses"
+    );
+    assert_eq!(issues.len(), 4, "issues {:?}", issues);
+}
+
+#[test]
+fn test_skeleton_bvh() {
+    let data = build_ape(
+        true,
+        &[
+            ("root", 255, [0.0, 0.0, 0.0]),
+            ("spine", 0, [0.0, 1.0, 0.0]),
+            ("head", 1, [0.0, 0.5, 0.25]),
+            ("arm", 1, [1.0, 0.0, 0.0]),
+        ],
+    );
+    let mut ape = Ape::read_auto(&mut Cursor::new(data)).expect("Failed to read ape");
+
+    // Turn the spine a quarter around Y, which moves its children in model space
+    let spine_to_model = CFMtx43A::from_rows(
+        CFVec3::new(0.0, 0.0, -1.0),
+        CFVec3::new(0.0, 1.0, 0.0),
+        CFVec3::new(1.0, 0.0, 0.0),
+        CFVec3::new(0.0, 1.0, 0.0),
+    );
+    let model_to_spine = spine_to_model
+        .inverse()
+        .expect("Rotation without an inverse");
+    let spine = &mut ape.bones[1];
+    spine.at_rest_bone_to_model_mtx = spine_to_model;
+    spine.at_rest_model_to_bone_mtx = model_to_spine;
+    spine.at_rest_bone_to_parent_mtx = spine_to_model;
+    spine.at_rest_parent_to_bone_mtx = model_to_spine;
+    for child in [2, 3] {
+        let bone = &mut ape.bones[child];
+        bone.at_rest_bone_to_model_mtx = bone.at_rest_bone_to_parent_mtx * spine_to_model;
+        bone.at_rest_model_to_bone_mtx = bone
+            .at_rest_bone_to_model_mtx
+            .inverse()
+            .expect("Rest matrix without an inverse");
+    }
+    assert!(ape.validate_skeleton().is_empty(), "valid rotated skeleton");
+
+    let bvh = ape.skeleton_to_bvh();
+    let offsets = bvh
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("OFFSET "))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        offsets,
+        [
+            "0.000000 0.000000 0.000000",
+            "0.000000 0.000000 0.000000",
+            "0.000000 1.000000 0.000000",
+            "0.250000 0.500000 0.000000",
+            "0.000000 0.000000 0.000000",
+            "0.000000 0.000000 -1.000000",
+            "0.000000 0.000000 0.000000",
+        ],
+        "bvh offsets of the model, bones and end sites in model space"
+    );
+
+    let data = build_ape(true, &[]);
+    let ape = Ape::read_auto(&mut Cursor::new(data)).expect("Failed to read ape");
+    let bvh = ape.skeleton_to_bvh();
+    assert!(
+        bvh.starts_with("HIERARCHY\nROOT test_model\n{\n"),
+        "bvh root without bones"
+    );
+    assert!(bvh.contains("\tEnd Site\n"), "end site below the root");
+    assert_eq!(
+        bvh.lines().last().map(|line| line.split(' ').count()),
+        Some(6),
+        "bvh channels of the root"
+    );
+}
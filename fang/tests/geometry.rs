@@ -0,0 +1,189 @@
+use fang::ape::geometry::{CFMtx43A, CFQuat, CFSphere, CFVec3};
+
+fn assert_vec3_near(actual: CFVec3, expected: CFVec3, message: &str) {
+    assert!(
+        actual.distance(&expected) < 1.0e-4,
+        "{}: {:?} is not {:?}",
+        message,
+        actual,
+        expected
+    );
+}
+
+/// A rotation of 90 degrees around the y axis, which turns x into -z
+fn quarter_turn_y() -> CFQuat {
+    let half_angle = std::f32::consts::FRAC_PI_4;
+    CFQuat::new(0.0, half_angle.sin(), 0.0, half_angle.cos())
+}
+
+#[test]
+fn test_mtx_transform() {
+    let mtx = CFMtx43A::from_scale_rotation_translation(
+        CFVec3::new(2.0, 2.0, 2.0),
+        quarter_turn_y(),
+        CFVec3::new(1.0, 2.0, 3.0),
+    );
+
+    assert_vec3_near(
+        mtx.transform_vector(&CFVec3::new(1.0, 0.0, 0.0)),
+        CFVec3::new(0.0, 0.0, -2.0),
+        "vector is scaled and rotated",
+    );
+    assert_vec3_near(
+        mtx.transform_point(&CFVec3::new(1.0, 0.0, 0.0)),
+        CFVec3::new(1.0, 2.0, 1.0),
+        "point is scaled, rotated and translated",
+    );
+    assert!((mtx.determinant() - 8.0).abs() < 1.0e-4, "determinant");
+}
+
+#[test]
+fn test_mtx_mul_inverse() {
+    let a = CFMtx43A::from_scale_rotation_translation(
+        CFVec3::new(1.0, 2.0, 0.5),
+        quarter_turn_y(),
+        CFVec3::new(-3.0, 0.5, 10.0),
+    );
+    let b = CFMtx43A::from_scale_rotation_translation(
+        CFVec3::new(3.0, 3.0, 3.0),
+        CFQuat::IDENTITY,
+        CFVec3::new(0.0, -1.0, 0.0),
+    );
+
+    let point = CFVec3::new(0.25, -4.0, 7.0);
+    assert_vec3_near(
+        (a * b).transform_point(&point),
+        b.transform_point(&a.transform_point(&point)),
+        "product applies the left matrix first",
+    );
+
+    let inverse = a.inverse().expect("Failed to invert matrix");
+    assert_vec3_near(
+        inverse.transform_point(&a.transform_point(&point)),
+        point,
+        "inverse undoes the matrix",
+    );
+    let identity = a * inverse;
+    for (row, expected) in [
+        (identity.x, CFMtx43A::IDENTITY.x),
+        (identity.y, CFMtx43A::IDENTITY.y),
+        (identity.z, CFMtx43A::IDENTITY.z),
+        (identity.p, CFMtx43A::IDENTITY.p),
+    ] {
+        assert_vec3_near(
+            row.xyz(),
+            expected.xyz(),
+            "matrix times inverse is identity",
+        );
+    }
+
+    let flat = CFMtx43A::from_scale_rotation_translation(
+        CFVec3::new(1.0, 0.0, 1.0),
+        CFQuat::IDENTITY,
+        CFVec3::ZERO,
+    );
+    assert!(flat.inverse().is_none(), "flattened matrix has no inverse");
+}
+
+#[test]
+fn test_mtx_decompose() {
+    let scale = CFVec3::new(1.5, 2.0, 0.5);
+    let rotation = quarter_turn_y();
+    let translation = CFVec3::new(4.0, -2.0, 1.0);
+    let mtx = CFMtx43A::from_scale_rotation_translation(scale, rotation, translation);
+
+    let (decomposed_translation, decomposed_rotation, decomposed_scale) = mtx.decompose();
+    assert_vec3_near(decomposed_translation, translation, "translation");
+    assert_vec3_near(decomposed_scale, scale, "scale");
+
+    // q and -q are the same rotation
+    let dot = rotation.x * decomposed_rotation.x
+        + rotation.y * decomposed_rotation.y
+        + rotation.z * decomposed_rotation.z
+        + rotation.w * decomposed_rotation.w;
+    assert!(
+        (dot.abs() - 1.0).abs() < 1.0e-4,
+        "rotation {:?}",
+        decomposed_rotation
+    );
+
+    let (_, identity_rotation, identity_scale) = CFMtx43A::IDENTITY.decompose();
+    assert_eq!(identity_rotation, CFQuat::IDENTITY, "identity rotation");
+    assert_vec3_near(identity_scale, CFVec3::new(1.0, 1.0, 1.0), "identity scale");
+}
+
+#[test]
+fn test_sphere() {
+    let a = CFSphere::new(CFVec3::ZERO, 1.0);
+    let b = CFSphere::new(CFVec3::new(4.0, 0.0, 0.0), 1.0);
+
+    assert!(a.contains(&CFVec3::new(0.5, 0.5, 0.5)), "point inside");
+    assert!(!a.contains(&CFVec3::new(1.0, 1.0, 0.0)), "point outside");
+    assert!(
+        a.contains_sphere(&CFSphere::new(CFVec3::new(0.5, 0.0, 0.0), 0.5)),
+        "sphere inside"
+    );
+    assert!(!a.contains_sphere(&b), "sphere outside");
+
+    let merged = a.merge(&b);
+    assert_vec3_near(merged.pos, CFVec3::new(2.0, 0.0, 0.0), "merged center");
+    assert!((merged.radius - 3.0).abs() < 1.0e-4, "merged radius");
+    assert!(
+        merged.contains_sphere(&a) && merged.contains_sphere(&b),
+        "merged sphere contains both"
+    );
+
+    let inner = CFSphere::new(CFVec3::new(0.25, 0.0, 0.0), 0.5);
+    assert_eq!(a.merge(&inner), a, "merging a contained sphere");
+    assert_eq!(inner.merge(&a), a, "merging into a containing sphere");
+
+    let mtx = CFMtx43A::from_scale_rotation_translation(
+        CFVec3::new(1.0, 3.0, 1.0),
+        quarter_turn_y(),
+        CFVec3::new(0.0, 5.0, 0.0),
+    );
+    let transformed = b.transform(&mtx);
+    assert_vec3_near(
+        transformed.pos,
+        CFVec3::new(0.0, 5.0, -4.0),
+        "transformed center",
+    );
+    assert!(
+        (transformed.radius - 3.0).abs() < 1.0e-4,
+        "transformed radius"
+    );
+}
+
+#[cfg(feature = "glam")]
+#[test]
+fn test_glam_conversion() {
+    let mtx = CFMtx43A::from_scale_rotation_translation(
+        CFVec3::new(2.0, 1.0, 1.0),
+        quarter_turn_y(),
+        CFVec3::new(1.0, 2.0, 3.0),
+    );
+    let point = CFVec3::new(0.5, -1.0, 2.0);
+
+    let affine: glam::Affine3A = mtx.into();
+    let glam_point = affine.transform_point3(point.into());
+    assert_vec3_near(
+        glam_point.into(),
+        mtx.transform_point(&point),
+        "glam transform",
+    );
+    assert_eq!(CFMtx43A::from(affine), mtx, "glam round trip");
+}
+
+#[cfg(feature = "mint")]
+#[test]
+fn test_mint_conversion() {
+    let mtx = CFMtx43A::from_scale_rotation_translation(
+        CFVec3::new(2.0, 1.0, 1.0),
+        quarter_turn_y(),
+        CFVec3::new(1.0, 2.0, 3.0),
+    );
+
+    let column_matrix: mint::ColumnMatrix3x4<f32> = mtx.into();
+    assert_eq!(column_matrix.w.z, 3.0, "translation is the last column");
+    assert_eq!(CFMtx43A::from(column_matrix), mtx, "mint round trip");
+}
@@ -1,12 +1,24 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fs::File,
-    io::{BufReader, Cursor, Read, Seek, SeekFrom},
+    io::{BufReader, Cursor, Read, Seek, SeekFrom, Write},
     rc::Rc,
 };
 
 use binrw::{BinReaderExt, BinWriterExt};
-use fang::mst::Mst;
+use fang::mst::{
+    archive::MstArchive,
+    builder::{MstBuilder, MstBuilderOrder},
+    crc::entry_crc,
+    diff::{MstContentChange, MstDiff},
+    entries::Entries,
+    entry::{CanonicalEntry, CanonicalSupportEntry, Entry},
+    filter::EntryFilter,
+    header::MstCompilers,
+    manifest::MstManifest,
+    patch::MstPatcher,
+    Mst, MstPlatformKnown, MstVersionKnown,
+};
 
 fn test_single(
     path: &str,
@@ -162,3 +174,975 @@ fn test_custom_xb() {
         (true, false, true, false, false),
     );
 }
+
+#[test]
+fn test_builder_crc() {
+    // Checksums are only computed when asked for, otherwise the kept ones are written
+    for write_crcs in [false, true] {
+        let mut mst_builder = MstBuilder::new(
+            MstVersionKnown::V180,
+            MstPlatformKnown::Xbox,
+            MstCompilers::default(),
+        );
+        mst_builder.add_entry_memory("first.csv".to_string(), b"first entry".to_vec(), Some(1));
+        mst_builder.add_entry_memory("second.csv".to_string(), vec![0x5a; 3000], Some(2));
+        mst_builder.keep_entry_crc("second.csv", 0x12345678);
+        mst_builder.set_write_crcs(write_crcs);
+
+        let mut out_file = Cursor::new(Vec::new());
+        mst_builder
+            .write(&mut out_file)
+            .expect("Failed to write Mst");
+
+        out_file
+            .seek(SeekFrom::Start(0))
+            .expect("Failed to seek file");
+        let mst = out_file.read_le::<Mst>().expect("Failed to parse Mst");
+
+        let entries = mst.collect_entries();
+        assert_eq!(entries.len(), 2, "entry count");
+
+        for entry in entries {
+            let data = &out_file.get_ref()[entry.offset()..entry.offset() + entry.size()];
+            let expected = match (write_crcs, entry.filename().as_str()) {
+                (true, _) => entry_crc(data),
+                (false, "second.csv") => 0x12345678,
+                (false, _) => 0,
+            };
+            assert_eq!(entry.crc, expected, "checksum of {}", entry.filename());
+        }
+    }
+}
+
+/// Write an archive with the given entries and support entries, entries get their name as data and the given flags
+fn write_source_mst(
+    path: &std::path::Path,
+    entries: &[(&str, u16)],
+    support_entries: &[(&str, u32)],
+    free_slots: (usize, usize),
+) -> Mst {
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::Xbox,
+        MstCompilers::default(),
+    );
+    for (name, flags) in entries {
+        mst_builder.add_entry_memory(name.to_string(), name.as_bytes().to_vec(), Some(1));
+        mst_builder.keep_entry_flags(name, *flags);
+        mst_builder.keep_entry_crc(name, 0x1000 + *flags as u32);
+    }
+    for (name, timestamp) in support_entries {
+        mst_builder.add_support_entry(CanonicalSupportEntry {
+            filename: (&name.to_string()).into(),
+            timestamp: *timestamp,
+        });
+    }
+    mst_builder.set_free_slots(free_slots.0, free_slots.1);
+
+    let mut file = File::create(path).expect("Failed to create file");
+    mst_builder.write(&mut file).expect("Failed to write Mst");
+    drop(file);
+
+    let mut file = BufReader::new(File::open(path).expect("Failed to open file"));
+    file.read_le::<Mst>().expect("Failed to parse Mst")
+}
+
+/// Write the builder to memory and read the result back
+fn rebuild_mst(mst_builder: MstBuilder) -> (Mst, Vec<u8>) {
+    let mut out_file = Cursor::new(Vec::new());
+    mst_builder
+        .write(&mut out_file)
+        .expect("Failed to write Mst");
+    out_file
+        .seek(SeekFrom::Start(0))
+        .expect("Failed to seek file");
+    let mst = out_file.read_le::<Mst>().expect("Failed to parse Mst");
+    (mst, out_file.into_inner())
+}
+
+#[test]
+fn test_builder_from_mst() {
+    // Like mst convert, keeping the version
+    let input_path = temp_path("builder_from_mst.mst");
+    let source = write_source_mst(
+        &input_path,
+        &[("first.csv", 0x0102), ("second.csv", 0)],
+        &[("level.wld", 2)],
+        (3, 2),
+    );
+
+    let mut mst_builder = MstBuilder::from_mst_empty(&source).expect("Failed to create builder");
+    mst_builder.add_mst_entries(&source, &input_path.to_string_lossy());
+    mst_builder.add_mst_support_entries(&source);
+    mst_builder.set_free_slots(
+        source.body.header.num_free_entries as usize,
+        source.body.header.num_free_support_entries as usize,
+    );
+    let (mst, data) = rebuild_mst(mst_builder);
+    std::fs::remove_file(&input_path).expect("Failed to remove file");
+
+    let entries = mst
+        .collect_entries()
+        .iter()
+        .map(|e| {
+            let entry_data = data[e.offset()..e.offset() + e.size()].to_vec();
+            (e.filename(), e.flags, e.crc, e.timestamp, entry_data)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        [
+            (
+                "first.csv".to_string(),
+                0x0102,
+                0x1102,
+                1,
+                b"first.csv".to_vec()
+            ),
+            (
+                "second.csv".to_string(),
+                0,
+                0x1000,
+                1,
+                b"second.csv".to_vec()
+            )
+        ],
+        "entries keep their flags, checksums, timestamps and data"
+    );
+
+    let support_entries = mst.collect_support_entries();
+    assert_eq!(support_entries.len(), 1, "support entry count");
+    assert_eq!(
+        support_entries[0].filename.to_string(),
+        "level.wld",
+        "support entry name"
+    );
+    assert_eq!(support_entries[0].timestamp, 2, "support entry timestamp");
+    assert_eq!(mst.body.header.num_free_entries, 3, "free entry count");
+    assert_eq!(
+        mst.body.header.num_free_support_entries, 2,
+        "free support entry count"
+    );
+}
+
+#[test]
+fn test_builder_combine_msts() {
+    // Like mst combine, the second archive takes precedence
+    let input1_path = temp_path("builder_combine_1.mst");
+    let input2_path = temp_path("builder_combine_2.mst");
+    let source1 = write_source_mst(&input1_path, &[("a.csv", 1)], &[("a.wld", 1)], (3, 2));
+    let source2 = write_source_mst(
+        &input2_path,
+        &[("b.csv", 2), ("a.csv", 3)],
+        &[("a.wld", 2), ("b.wld", 3)],
+        (0, 0),
+    );
+
+    let mut mst_builder = MstBuilder::from_mst_empty(&source1).expect("Failed to create builder");
+    mst_builder.add_mst_entries(&source1, &input1_path.to_string_lossy());
+    mst_builder.add_mst_entries(&source2, &input2_path.to_string_lossy());
+    mst_builder.add_mst_support_entries(&source1);
+    mst_builder.add_mst_support_entries(&source2);
+    let (mst, _) = rebuild_mst(mst_builder);
+    for path in [&input1_path, &input2_path] {
+        std::fs::remove_file(path).expect("Failed to remove file");
+    }
+
+    let entries = mst
+        .collect_entries()
+        .iter()
+        .map(|e| (e.filename(), e.flags))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        [("a.csv".to_string(), 3), ("b.csv".to_string(), 2)],
+        "combined entries with the flags of the second archive"
+    );
+
+    let support_entries = mst
+        .collect_support_entries()
+        .iter()
+        .map(|e| (e.filename.to_string(), e.timestamp))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        support_entries,
+        [("a.wld".to_string(), 2), ("b.wld".to_string(), 3)],
+        "combined support entries, the second archive taking precedence"
+    );
+}
+
+#[test]
+fn test_manifest_round_trip() {
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::Xbox,
+        MstCompilers::default(),
+    );
+    mst_builder.add_entry_memory("first.csv".to_string(), b"first entry".to_vec(), Some(1));
+    mst_builder.add_entry_memory("second.tga".to_string(), vec![0x5a; 3000], Some(2));
+    mst_builder.add_entry_memory("third.ape".to_string(), vec![0xa5; 2048], Some(3));
+    // Names are read a byte per character, bytes past ASCII have to survive the JSON manifest
+    mst_builder.add_entry_memory("caf\u{e9}.csv".to_string(), b"fourth".to_vec(), Some(4));
+    mst_builder.add_support_entry(CanonicalSupportEntry {
+        filename: (&"level.wld".to_string()).into(),
+        timestamp: 4,
+    });
+    mst_builder.set_free_slots(2, 1);
+    mst_builder.set_write_crcs(true);
+
+    let mut original = Cursor::new(Vec::new());
+    mst_builder
+        .write(&mut original)
+        .expect("Failed to write Mst");
+
+    // Unpack every entry next to each other, like mst unpack does
+    original
+        .seek(SeekFrom::Start(0))
+        .expect("Failed to seek file");
+    let mst = original.read_le::<Mst>().expect("Failed to parse Mst");
+    assert!(
+        original
+            .get_ref()
+            .windows(9)
+            .any(|name| name == b"caf\xe9.csv"),
+        "non-ASCII name stored a byte per character"
+    );
+
+    let manifest_json =
+        serde_json::to_string(&MstManifest::from_mst(&mst)).expect("Failed to write manifest");
+    let manifest: MstManifest =
+        serde_json::from_str(&manifest_json).expect("Failed to read manifest");
+
+    let data_dir = temp_path("manifest_round_trip");
+    std::fs::create_dir_all(&data_dir).expect("Failed to create directory");
+    for manifest_entry in &manifest.entries {
+        let entry = &manifest_entry.entry;
+        std::fs::write(
+            data_dir.join(&manifest_entry.path),
+            &original.get_ref()[entry.offset()..entry.offset() + entry.size()],
+        )
+        .expect("Failed to write entry data");
+    }
+
+    let mut repacked = Cursor::new(Vec::new());
+    manifest
+        .write(&data_dir, &mut repacked)
+        .expect("Failed to repack Mst");
+    std::fs::remove_dir_all(&data_dir).expect("Failed to remove directory");
+
+    assert_eq!(
+        repacked.get_ref().len(),
+        original.get_ref().len(),
+        "repacked length"
+    );
+    assert!(
+        repacked.get_ref() == original.get_ref(),
+        "repacked archive matches the original byte for byte"
+    );
+}
+
+/// Path in the temporary directory that other test runs won't use at the same time
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("fang_test_{}_{}", std::process::id(), name))
+}
+
+/// Reader that counts how often it's seeked, to tell whether entries share it
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    seeks: Rc<Cell<usize>>,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for CountingReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.seeks.set(self.seeks.get() + 1);
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn test_builder_sources() {
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::Xbox,
+        MstCompilers::default(),
+    );
+    mst_builder.set_order(MstBuilderOrder::SourceLayout);
+
+    let seeks = Rc::new(Cell::new(0));
+    let first_source = mst_builder.add_source(CountingReader {
+        inner: Cursor::new(b"0123456789".to_vec()),
+        seeks: seeks.clone(),
+    });
+    let second_source = mst_builder.add_source(Cursor::new(b"abcdefghij".to_vec()));
+
+    // Out of order within and across sources, and one from memory in between
+    mst_builder.add_entry_source("late.csv".to_string(), first_source, 6, 4, Some(1));
+    mst_builder.add_entry_source("other.csv".to_string(), second_source, 2, 3, Some(2));
+    mst_builder.add_entry_memory("memory.csv".to_string(), b"memory".to_vec(), Some(3));
+    mst_builder.add_entry_source("early.csv".to_string(), first_source, 0, 3, Some(4));
+
+    let mut out_file = Cursor::new(Vec::new());
+    mst_builder
+        .write(&mut out_file)
+        .expect("Failed to write Mst");
+    assert_eq!(seeks.get(), 2, "one reader for both entries of a source");
+
+    let mut archive = MstArchive::new(out_file).expect("Failed to parse Mst");
+    let names = archive
+        .entries()
+        .map(|entry| entry.filename())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["late.csv", "other.csv", "memory.csv", "early.csv"],
+        "entry table in insertion order"
+    );
+
+    let mut offsets = Vec::new();
+    for (name, data) in [
+        ("early.csv", &b"012"[..]),
+        ("late.csv", b"6789"),
+        ("other.csv", b"cde"),
+        ("memory.csv", b"memory"),
+    ] {
+        let entry_data = archive.read_entry(name).expect("Failed to read entry");
+        assert_eq!(entry_data, data, "entry data of {}", name);
+        offsets.push(archive.entry(name).expect("Missing entry").offset());
+    }
+    assert!(
+        offsets.windows(2).all(|pair| pair[0] < pair[1]),
+        "data laid out by source and offset, memory last: {:?}",
+        offsets
+    );
+
+    // Running out of source data is an error instead of a short entry
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::Xbox,
+        MstCompilers::default(),
+    );
+    let source = mst_builder.add_source(Cursor::new(b"short".to_vec()));
+    mst_builder.add_entry_source("long.csv".to_string(), source, 2, 10, Some(1));
+    let result = mst_builder.write(&mut Cursor::new(Vec::new()));
+    assert!(
+        matches!(
+            result,
+            Err(fang::Error::SourceTooShort {
+                read: 3,
+                expected: 10,
+                ..
+            })
+        ),
+        "short source {:?}",
+        result
+    );
+}
+
+fn build_entry_names(order: MstBuilderOrder) -> Vec<String> {
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::Xbox,
+        MstCompilers::default(),
+    );
+    mst_builder.set_order(order);
+    for name in ["b.csv", "a_b.csv", "ab.csv"] {
+        mst_builder.add_entry_memory(name.to_string(), name.as_bytes().to_vec(), Some(1));
+    }
+
+    let mut out_file = Cursor::new(Vec::new());
+    mst_builder
+        .write(&mut out_file)
+        .expect("Failed to write Mst");
+
+    out_file
+        .seek(SeekFrom::Start(0))
+        .expect("Failed to seek file");
+    let mst = out_file.read_le::<Mst>().expect("Failed to parse Mst");
+
+    mst.collect_entries().iter().map(|e| e.filename()).collect()
+}
+
+#[test]
+fn test_builder_order() {
+    assert_eq!(
+        build_entry_names(MstBuilderOrder::Insertion),
+        ["b.csv", "a_b.csv", "ab.csv"],
+        "insertion order"
+    );
+    assert_eq!(
+        build_entry_names(MstBuilderOrder::Sorted),
+        ["ab.csv", "a_b.csv", "b.csv"],
+        "sorted order"
+    );
+}
+
+#[test]
+fn test_archive_read() {
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V160,
+        MstPlatformKnown::Xbox,
+        MstCompilers::default(),
+    );
+    mst_builder.add_entry_memory("first.csv".to_string(), b"first entry".to_vec(), Some(1));
+    mst_builder.add_entry_memory("second.csv".to_string(), vec![0x5a; 3000], Some(2));
+
+    let mut out_file = Cursor::new(Vec::new());
+    mst_builder
+        .write(&mut out_file)
+        .expect("Failed to write Mst");
+
+    let mut archive = MstArchive::new(out_file).expect("Failed to parse Mst");
+    assert_eq!(archive.version(), MstVersionKnown::V160, "version");
+    assert_eq!(archive.platform(), MstPlatformKnown::Xbox, "platform");
+    assert_eq!(archive.entries().count(), 2, "entry count");
+
+    // Lookups ignore case
+    let data = archive
+        .read_entry("FIRST.CSV")
+        .expect("Failed to read entry");
+    assert_eq!(data, b"first entry", "entry data");
+
+    // Reads are bounded to the entry and seeks are relative to it
+    let mut entry_reader = archive
+        .open_entry("second.csv")
+        .expect("Failed to open entry");
+    entry_reader
+        .seek(SeekFrom::End(-10))
+        .expect("Failed to seek entry");
+    let mut tail = Vec::new();
+    entry_reader
+        .read_to_end(&mut tail)
+        .expect("Failed to read entry");
+    assert_eq!(tail, vec![0x5a; 10], "entry tail");
+
+    assert!(archive.open_entry("third.csv").is_err(), "missing entry");
+
+    // An archive cut short in the middle of an entry fails the read instead of returning less data
+    let mut data = archive.into_inner().into_inner();
+    data.truncate(data.len() - 1000);
+    let mut archive = MstArchive::new(Cursor::new(data)).expect("Failed to parse Mst");
+    let second_end = archive.entry("second.csv").map(|e| e.offset() + e.size());
+    match archive.read_entry("second.csv") {
+        Err(fang::Error::Truncated { offset, expected }) => {
+            assert_eq!(offset + expected, second_end.unwrap() as u64, "entry end")
+        }
+        other => panic!("entry past the end of the data read as {:?}", other),
+    }
+    let mut entry_reader = archive
+        .open_entry("second.csv")
+        .expect("Failed to open entry");
+    let error = std::io::copy(&mut entry_reader, &mut std::io::sink())
+        .expect_err("copied an entry past the end of the data");
+    assert_eq!(
+        error.kind(),
+        std::io::ErrorKind::UnexpectedEof,
+        "error kind"
+    );
+}
+
+fn build_diff_archive(entries: &[(&str, &[u8], u32)], ape_compiler: u32) -> Cursor<Vec<u8>> {
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::Xbox,
+        MstCompilers {
+            ape_compiler_version: ape_compiler,
+            ..Default::default()
+        },
+    );
+    for (name, data, timestamp) in entries {
+        mst_builder.add_entry_memory(name.to_string(), data.to_vec(), Some(*timestamp));
+    }
+
+    let mut out_file = Cursor::new(Vec::new());
+    mst_builder
+        .write(&mut out_file)
+        .expect("Failed to write Mst");
+    out_file
+}
+
+#[test]
+fn test_diff() {
+    let old_file = build_diff_archive(
+        &[
+            ("same.csv", b"same", 1),
+            ("removed.csv", b"removed", 1),
+            ("resized.csv", b"short", 1),
+            ("touched.csv", b"touched", 1),
+            ("edited.csv", b"before", 1),
+        ],
+        1,
+    );
+    let new_file = build_diff_archive(
+        &[
+            ("SAME.CSV", b"same", 1),
+            ("resized.csv", b"much longer", 1),
+            ("touched.csv", b"touched", 2),
+            ("edited.csv", b"after!", 1),
+            ("added.csv", b"added", 1),
+        ],
+        2,
+    );
+
+    let mut old = MstArchive::new(old_file).expect("Failed to parse old Mst");
+    let mut new = MstArchive::new(new_file).expect("Failed to parse new Mst");
+    let diff = MstDiff::from_archives(&mut old, &mut new).expect("Failed to diff");
+
+    let header_fields = diff
+        .header
+        .iter()
+        .map(|change| change.field.as_str())
+        .collect::<Vec<_>>();
+    assert!(
+        header_fields.contains(&"ape_compiler_version"),
+        "compiler version change"
+    );
+    assert!(!header_fields.contains(&"version"), "version change");
+
+    let added = diff.added.iter().map(|e| e.filename()).collect::<Vec<_>>();
+    assert_eq!(added, vec!["added.csv"], "added entries");
+    let removed = diff
+        .removed
+        .iter()
+        .map(|e| e.filename())
+        .collect::<Vec<_>>();
+    assert_eq!(removed, vec!["removed.csv"], "removed entries");
+
+    let changed = diff
+        .changed
+        .iter()
+        .map(|c| (c.new.filename(), c.resized, c.retimestamped, c.content))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changed,
+        vec![
+            (
+                "resized.csv".to_string(),
+                true,
+                false,
+                MstContentChange::Changed
+            ),
+            (
+                "touched.csv".to_string(),
+                false,
+                true,
+                MstContentChange::Unchanged
+            ),
+            (
+                "edited.csv".to_string(),
+                false,
+                false,
+                MstContentChange::Changed
+            ),
+        ],
+        "changed entries"
+    );
+
+    // Without data, checksums still catch the edit
+    let table_diff = MstDiff::from_tables(old.mst(), new.mst()).expect("Failed to diff tables");
+    assert_eq!(table_diff.changed.len(), 3, "changed entries from tables");
+}
+
+#[test]
+fn test_errors() {
+    let mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::Xbox,
+        MstCompilers::default(),
+    );
+    let mut out_file = Cursor::new(Vec::new());
+    mst_builder
+        .write(&mut out_file)
+        .expect("Failed to write Mst");
+    let data = out_file.into_inner();
+
+    // The minor version is the second byte of the version after the magic
+    let mut unknown_version = data.clone();
+    unknown_version[5] = 9;
+    match MstArchive::new(Cursor::new(unknown_version)) {
+        Err(fang::Error::UnknownVersion { version }) => {
+            assert_eq!(version.minor(), 9, "minor version in error")
+        }
+        other => panic!("expected an unknown version error, got {:?}", other.err()),
+    }
+
+    let mut bad_magic = data.clone();
+    bad_magic[..4].copy_from_slice(b"NOPE");
+    assert!(
+        matches!(
+            MstArchive::new(Cursor::new(bad_magic)).err(),
+            Some(fang::Error::BadMagic { pos: 0 })
+        ),
+        "bad magic error"
+    );
+
+    assert!(
+        matches!(
+            MstArchive::new(Cursor::new(data[..40].to_vec())).err(),
+            Some(fang::Error::Truncated {
+                offset: 40,
+                expected: 4
+            })
+        ),
+        "truncated error"
+    );
+
+    let mut archive = MstArchive::new(Cursor::new(data)).expect("Failed to parse Mst");
+    assert!(
+        matches!(
+            archive.read_entry("missing.csv"),
+            Err(fang::Error::EntryNotFound { .. })
+        ),
+        "entry not found error"
+    );
+}
+
+#[test]
+fn test_lenient_read() {
+    for version in [
+        MstVersionKnown::V180,
+        MstVersionKnown::V170,
+        MstVersionKnown::V160,
+    ] {
+        let mut mst_builder =
+            MstBuilder::new(version, MstPlatformKnown::Xbox, MstCompilers::default());
+        mst_builder.set_free_slots(4, 2);
+        mst_builder.add_entry_memory("first.csv".to_string(), b"first".to_vec(), Some(1));
+        mst_builder.add_entry_memory("second.csv".to_string(), b"second".to_vec(), Some(2));
+
+        let mut out_file = Cursor::new(Vec::new());
+        mst_builder
+            .write(&mut out_file)
+            .expect("Failed to write Mst");
+
+        // Pretend the archive comes from a build with a patch version
+        let mut data = out_file.into_inner();
+        data[4] = 1;
+        assert!(
+            MstArchive::new(Cursor::new(data.clone())).is_err(),
+            "strict read of {:?}",
+            version
+        );
+
+        let mut archive =
+            MstArchive::new_lenient(Cursor::new(data)).expect("Failed to parse Mst leniently");
+        let guess = *archive.layout_guess().expect("Missing layout guess");
+        assert_eq!(guess.layout, version, "guessed layout");
+        assert_eq!(guess.plausible_entries, 2, "plausible entries");
+        assert!(guess.fits_data_offset, "tables fit data offset");
+
+        let data = archive
+            .read_entry("second.csv")
+            .expect("Failed to read entry");
+        assert_eq!(data, b"second", "entry data of {:?}", version);
+    }
+}
+
+#[test]
+fn test_entry_filter() {
+    let entry = |name: &str, size: u32, timestamp: u32| CanonicalEntry {
+        filename: (&name.to_string()).into(),
+        size,
+        timestamp,
+        ..Default::default()
+    };
+    let ape = entry("gc_box.ape", 4000, 100);
+    let tga = entry("GC_BOX.TGA", 100, 200);
+    let csv = entry("level.csv", 10, 300);
+
+    assert!(
+        EntryFilter::new().matches(&ape),
+        "empty filter matches everything"
+    );
+
+    let mut glob_filter = EntryFilter::new();
+    glob_filter
+        .add_include_glob("gc_*")
+        .expect("Failed to add glob");
+    glob_filter
+        .add_exclude_glob("*.ape")
+        .expect("Failed to add glob");
+    assert!(!glob_filter.matches(&ape), "excluded glob");
+    assert!(glob_filter.matches(&tga), "included glob ignores case");
+    assert!(!glob_filter.matches(&csv), "not included glob");
+
+    let mut regex_filter = EntryFilter::new();
+    regex_filter
+        .add_include_regex(r"^level\.")
+        .expect("Failed to add regex");
+    assert!(regex_filter.matches(&csv), "included regex");
+    assert!(!regex_filter.matches(&ape), "not included regex");
+
+    let mut extension_filter = EntryFilter::new();
+    extension_filter.add_extension(".tga");
+    extension_filter.add_extension("csv");
+    assert!(!extension_filter.matches(&ape), "other extension");
+    assert!(extension_filter.matches(&tga), "extension ignores case");
+    assert!(extension_filter.matches(&csv), "extension without dot");
+
+    let mut range_filter = EntryFilter::new();
+    range_filter.set_size_range(Some(50), None);
+    range_filter.set_timestamp_range(None, Some(200));
+    assert!(range_filter.matches(&ape), "within ranges");
+    assert!(range_filter.matches(&tga), "inclusive timestamp range");
+    assert!(!range_filter.matches(&csv), "outside ranges");
+}
+
+#[test]
+fn test_patch() {
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::Xbox,
+        MstCompilers::default(),
+    );
+    mst_builder.set_order(MstBuilderOrder::Sorted);
+    mst_builder.set_free_slots(1, 0);
+    mst_builder.add_entry_memory("a.csv".to_string(), vec![1; 100], Some(1));
+    mst_builder.add_entry_memory("c.csv".to_string(), vec![3; 100], Some(3));
+
+    let mut out_file = Cursor::new(Vec::new());
+    mst_builder
+        .write(&mut out_file)
+        .expect("Failed to write Mst");
+    let original_len = out_file.get_ref().len();
+
+    let mut patcher = MstPatcher::new(out_file).expect("Failed to parse Mst");
+    patcher.set_write_crcs(true);
+
+    // Fits in the aligned slot, stays in place
+    patcher
+        .replace_entry("A.CSV", &[4; 1000], Some(4))
+        .expect("Failed to replace entry");
+    // Too big for the slot, gets appended
+    patcher
+        .replace_entry("c.csv", &[5; 5000], Some(5))
+        .expect("Failed to replace entry");
+    patcher
+        .add_entry("b.csv", b"added", Some(6))
+        .expect("Failed to add entry");
+    assert!(
+        patcher.add_entry("d.csv", b"no room", None).is_err(),
+        "add without free slots"
+    );
+    patcher
+        .delete_entry("a.csv")
+        .expect("Failed to delete entry");
+    patcher
+        .add_entry("d.csv", b"reused slot", Some(7))
+        .expect("Failed to add entry");
+    assert_eq!(patcher.free_entry_count(), 0, "free entry count");
+
+    let out_file = patcher.finish().expect("Failed to finish patch");
+    assert!(out_file.get_ref().len() > original_len, "appended data");
+
+    let mut archive = MstArchive::new(out_file).expect("Failed to parse patched Mst");
+    let names = archive
+        .entries()
+        .map(|entry| entry.filename())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["b.csv", "c.csv", "d.csv"], "entry names");
+    assert_eq!(archive.mst().body.header.num_entries, 3, "entry count");
+    assert_eq!(
+        archive.mst().body.header.num_free_entries,
+        0,
+        "free entry count"
+    );
+
+    let c_entry = archive.entry("c.csv").expect("Missing entry").clone();
+    assert_eq!(c_entry.offset() % 2048, 0, "appended entry alignment");
+    assert_eq!(c_entry.crc, entry_crc(&[5; 5000]), "entry crc");
+
+    for (name, data) in [
+        ("b.csv", b"added".to_vec()),
+        ("c.csv", vec![5; 5000]),
+        ("d.csv", b"reused slot".to_vec()),
+    ] {
+        let entry_data = archive.read_entry(name).expect("Failed to read entry");
+        assert_eq!(entry_data, data, "entry data of {}", name);
+    }
+}
+
+#[test]
+fn test_patch_shared_data() {
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::Xbox,
+        MstCompilers::default(),
+    );
+    mst_builder.set_order(MstBuilderOrder::Sorted);
+    mst_builder.add_entry_memory("a.csv".to_string(), vec![1; 100], Some(1));
+    mst_builder.add_entry_memory("b.csv".to_string(), vec![2; 100], Some(2));
+
+    let mut out_file = Cursor::new(Vec::new());
+    mst_builder
+        .write(&mut out_file)
+        .expect("Failed to write Mst");
+
+    // Point b.csv at the data of a.csv
+    out_file.set_position(0);
+    let mut mst = out_file.read_le::<Mst>().expect("Failed to parse Mst");
+    let mut entries = mst.body.entries.to_canonical();
+    entries.entries[1].offset = entries.entries[0].offset;
+    mst.body.entries = Entries::from_canonical(MstVersionKnown::V180, entries);
+    out_file.set_position(0);
+    out_file
+        .write_le_args(&mst, (Rc::new(RefCell::new(Vec::new())),))
+        .expect("Failed to write Mst");
+
+    let mut patcher = MstPatcher::new(out_file).expect("Failed to parse Mst");
+    patcher
+        .replace_entry("a.csv", &[3; 10], Some(3))
+        .expect("Failed to replace entry");
+    let out_file = patcher.finish().expect("Failed to finish patch");
+
+    let mut archive = MstArchive::new(out_file).expect("Failed to parse patched Mst");
+    let a_offset = archive.entry("a.csv").expect("Missing entry").offset();
+    let b_offset = archive.entry("b.csv").expect("Missing entry").offset();
+    assert_ne!(a_offset, b_offset, "shared data replaced elsewhere");
+    assert_eq!(
+        archive.read_entry("a.csv").expect("Failed to read entry"),
+        [3; 10],
+        "entry data of a.csv"
+    );
+    assert_eq!(
+        archive.read_entry("b.csv").expect("Failed to read entry"),
+        [1; 100],
+        "entry data of b.csv left alone"
+    );
+}
+
+/// File that keeps its first bytes in memory, and only records where anything past them is written
+struct SparseFile {
+    head: Vec<u8>,
+    position: u64,
+    writes: Vec<(u64, usize)>,
+}
+
+impl Read for SparseFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let start = (self.position as usize).min(self.head.len());
+        let len = buf.len().min(self.head.len() - start);
+        buf[..len].copy_from_slice(&self.head[start..start + len]);
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl Write for SparseFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.position + buf.len() as u64 <= self.head.len() as u64 {
+            true => {
+                let start = self.position as usize;
+                self.head[start..start + buf.len()].copy_from_slice(buf);
+            }
+            false => self.writes.push((self.position, buf.len())),
+        }
+        self.position += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for SparseFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(offset) => (self.head.len() as i64 + offset) as u64,
+            SeekFrom::Current(offset) => (self.position as i64 + offset) as u64,
+        };
+        Ok(self.position)
+    }
+}
+
+#[test]
+fn test_patch_ps2() {
+    // The stripped archive ends where the entry data would start
+    let file = SparseFile {
+        head: std::fs::read("../resources/mst/ma_ps2_1.stripped.mst").expect("Failed to read file"),
+        position: 0,
+        writes: Vec::new(),
+    };
+    let mut patcher = MstPatcher::new(file).expect("Failed to parse Mst");
+
+    // PlayStation 2 data is only 16 byte aligned, sounds.csv has 1888 bytes before snd_bot.csv
+    patcher
+        .replace_entry("sounds.csv", &[1; 1888], Some(1))
+        .expect("Failed to replace entry");
+    // One byte more than fits before snd_veh.csv, appended at the end of the archive
+    patcher
+        .replace_entry("snd_bot.csv", &[2; 22289], Some(2))
+        .expect("Failed to replace entry");
+    patcher
+        .add_entry("added.csv", b"added", Some(3))
+        .expect("Failed to add entry");
+    let file = patcher.finish().expect("Failed to finish patch");
+
+    assert_eq!(
+        file.writes,
+        [(2212528, 1888), (505762528, 22289), (505784832, 5),],
+        "entry data writes"
+    );
+
+    let mst = Cursor::new(&file.head)
+        .read_le::<Mst>()
+        .expect("Failed to parse patched Mst");
+    assert_eq!(mst.body.header.bytes_in_file, 505784837, "bytes in file");
+    let entries = mst.collect_entries();
+    for (name, offset, size) in [
+        ("sounds.csv", 2212528, 1888),
+        ("snd_bot.csv", 505762528, 22289),
+        ("snd_veh.csv", 2236704, 9228),
+        ("added.csv", 505784832, 5),
+    ] {
+        let entry = entries
+            .iter()
+            .find(|entry| entry.filename() == name)
+            .expect("Missing entry");
+        assert_eq!(
+            (entry.offset(), entry.size()),
+            (offset, size),
+            "offset and size of {}",
+            name
+        );
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mmap_read() {
+    use fang::mst::mmap::MstMmap;
+
+    let mut mst_builder = MstBuilder::new(
+        MstVersionKnown::V180,
+        MstPlatformKnown::GameCube,
+        MstCompilers::default(),
+    );
+    mst_builder.add_entry_memory("first.csv".to_string(), b"first entry".to_vec(), Some(1));
+
+    let path = std::env::temp_dir().join("fang_test_mmap_read.mst");
+    let mut out_file = File::create(&path).expect("Failed to create file");
+    mst_builder
+        .write(&mut out_file)
+        .expect("Failed to write Mst");
+    drop(out_file);
+
+    let mst_mmap = MstMmap::open(&path).expect("Failed to map Mst");
+    assert_eq!(
+        mst_mmap
+            .data("First.csv")
+            .expect("Failed to get entry data"),
+        b"first entry",
+        "entry data"
+    );
+
+    drop(mst_mmap);
+    std::fs::remove_file(&path).expect("Failed to remove file");
+}